@@ -0,0 +1,72 @@
+//! Compares canonical name encoding against going through `serde_json`
+//!
+//! Run with `cargo bench --bench names`
+extern crate libcantal;
+extern crate serde_json;
+
+use std::time::{Duration, Instant};
+
+use libcantal::{Name, NameEncoder, NameVisitor};
+use serde_json::{Map, Value};
+
+
+const ITERATIONS: u32 = 100_000;
+
+struct MapVisitor<'a>(&'a mut Map<String, Value>);
+
+impl<'a> NameVisitor for MapVisitor<'a> {
+    fn visit_pair(&mut self, key: &str, value: &str) {
+        self.0.insert(key.to_string(), Value::String(value.to_string()));
+    }
+}
+
+// This is what `start` used to do: build a `serde_json::Value` to get
+// keys sorted and then serialize it
+fn via_serde(name: &dyn Name) -> String {
+    let mut map = Map::new();
+    name.visit(&mut MapVisitor(&mut map));
+    serde_json::to_string(&Value::Object(map)).expect("can serialize")
+}
+
+fn measure<F: FnMut()>(mut f: F) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn main() {
+    let names = vec![
+        ("short", serde_json::json!({"metric": "requests"})),
+        ("typical", serde_json::json!({
+            "group": "http",
+            "metric": "requests",
+            "status": "200",
+            "method": "GET",
+        })),
+        ("escaped", serde_json::json!({
+            "group": "log",
+            "metric": "messages",
+            "target": "app::\"quoted\"\n\u{1}\\",
+        })),
+    ];
+    let mut encoder = NameEncoder::new();
+    let mut buf = String::with_capacity(256);
+    for (title, name) in &names {
+        buf.clear();
+        encoder.encode(name, &mut buf);
+        assert_eq!(buf, via_serde(name));
+
+        let old = measure(|| {
+            let _ = via_serde(name);
+        });
+        let new = measure(|| {
+            buf.clear();
+            encoder.encode(name, &mut buf);
+        });
+        println!("{:>8}: serde_json {:>8?}/iter, encoder {:>8?}/iter ({:.1}x)",
+                 title, old, new,
+                 old.as_secs_f64() / new.as_secs_f64().max(1e-12));
+    }
+}
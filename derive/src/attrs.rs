@@ -0,0 +1,54 @@
+use syn::{Attribute, LitStr, Result};
+use syn::meta::ParseNestedMeta;
+
+
+/// Options of `#[cantal(...)]` attributes on a struct or a field
+#[derive(Default)]
+pub struct Attrs {
+    pub rename: Option<LitStr>,
+    pub skip: bool,
+    pub nested: bool,
+    pub labels: Vec<(String, LitStr)>,
+}
+
+fn parse_labels(meta: ParseNestedMeta, labels: &mut Vec<(String, LitStr)>)
+    -> Result<()>
+{
+    meta.parse_nested_meta(|pair| {
+        let key = match pair.path.get_ident() {
+            Some(ident) => ident.to_string(),
+            None => return Err(pair.error("label key must be an identifier")),
+        };
+        let value: LitStr = pair.value()?.parse()?;
+        labels.push((key, value));
+        Ok(())
+    })
+}
+
+impl Attrs {
+    pub fn parse(attrs: &[Attribute]) -> Result<Attrs> {
+        let mut result = Attrs::default();
+        for attr in attrs {
+            if !attr.path().is_ident("cantal") {
+                continue;
+            }
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    result.rename = Some(meta.value()?.parse()?);
+                    Ok(())
+                } else if meta.path.is_ident("skip") {
+                    result.skip = true;
+                    Ok(())
+                } else if meta.path.is_ident("nested") {
+                    result.nested = true;
+                    Ok(())
+                } else if meta.path.is_ident("label") {
+                    parse_labels(meta, &mut result.labels)
+                } else {
+                    Err(meta.error("unknown cantal attribute"))
+                }
+            })?;
+        }
+        Ok(result)
+    }
+}
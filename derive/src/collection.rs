@@ -0,0 +1,136 @@
+use proc_macro2::TokenStream;
+use syn::{Data, DeriveInput, Error, Fields, LitStr, Result};
+
+use attrs::Attrs;
+
+
+pub fn derive(input: DeriveInput) -> Result<TokenStream> {
+    let attrs = Attrs::parse(&input.attrs)?;
+    if attrs.rename.is_some() || attrs.skip || attrs.nested {
+        return Err(Error::new_spanned(&input.ident,
+            "only `label` is supported on the struct"));
+    }
+    if attrs.labels.iter().any(|(key, _)| key == "metric") {
+        return Err(Error::new_spanned(&input.ident,
+            "`metric` is set from the field name, use `rename` on fields"));
+    }
+    let fields = match input.data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => &fields.named,
+            _ => return Err(Error::new_spanned(&input.ident,
+                "Collection can only be derived for structs \
+                 with named fields")),
+        },
+        _ => return Err(Error::new_spanned(&input.ident,
+            "Collection can only be derived for structs")),
+    };
+
+    let mut metrics = Vec::new();
+    for field in fields {
+        let field_attrs = Attrs::parse(&field.attrs)?;
+        if field_attrs.skip {
+            continue;
+        }
+        let ident = field.ident.as_ref().expect("named field");
+        if field_attrs.nested {
+            if field_attrs.rename.is_some() || !field_attrs.labels.is_empty()
+            {
+                return Err(Error::new_spanned(field,
+                    "`nested` can't be combined with other attributes"));
+            }
+            metrics.push(quote! {
+                ::libcantal::Collection::visit(&self.#ident, visitor);
+            });
+            continue;
+        }
+        let metric = field_attrs.rename
+            .unwrap_or_else(|| LitStr::new(&ident.to_string(), ident.span()));
+        let mut pairs = vec![("metric".to_string(), metric)];
+        // field labels override struct labels
+        for (key, value) in field_attrs.labels {
+            if key == "metric" {
+                return Err(Error::new_spanned(field,
+                    "use `rename` to change the metric key"));
+            }
+            if pairs.iter().any(|(k, _)| *k == key) {
+                return Err(Error::new_spanned(field,
+                    format!("duplicate label {:?}", key)));
+            }
+            pairs.push((key, value));
+        }
+        for (key, value) in &attrs.labels {
+            if !pairs.iter().any(|(k, _)| k == key) {
+                pairs.push((key.clone(), value.clone()));
+            }
+        }
+        pairs.sort_by(|a, b| a.0.cmp(&b.0));
+        let keys = pairs.iter().map(|(k, _)| k);
+        let values = pairs.iter().map(|(_, v)| v);
+        metrics.push(quote! {
+            visitor.metric(
+                &::libcantal::StaticName(&[#((#keys, #values)),*]),
+                &self.#ident);
+        });
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) =
+        input.generics.split_for_impl();
+    // parenthesis are needed for `dyn ::path` to work on rust 2015
+    Ok(quote! {
+        impl #impl_generics ::libcantal::Collection for #name #ty_generics
+            #where_clause
+        {
+            fn visit<'x>(&'x self,
+                visitor: &mut dyn (::libcantal::Visitor<'x>))
+            {
+                #(#metrics)*
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use syn::parse_quote;
+
+    use super::derive;
+
+    fn error(input: ::syn::DeriveInput) -> String {
+        derive(input).err().expect("derive fails").to_string()
+    }
+
+    #[test]
+    fn metric_label() {
+        assert_eq!(error(parse_quote! {
+            #[cantal(label(metric = "requests"))]
+            struct Metrics { count: Counter }
+        }), "`metric` is set from the field name, use `rename` on fields");
+        assert_eq!(error(parse_quote! {
+            struct Metrics {
+                #[cantal(label(metric = "requests"))]
+                count: Counter,
+            }
+        }), "use `rename` to change the metric key");
+    }
+
+    #[test]
+    fn invalid_attributes() {
+        assert_eq!(error(parse_quote! {
+            #[cantal(skip)]
+            struct Metrics { count: Counter }
+        }), "only `label` is supported on the struct");
+        assert_eq!(error(parse_quote! {
+            struct Metrics {
+                #[cantal(nested, rename = "x")]
+                inner: Inner,
+            }
+        }), "`nested` can't be combined with other attributes");
+        assert_eq!(error(parse_quote! {
+            struct Metrics {
+                #[cantal(label(status = "200", status = "500"))]
+                count: Counter,
+            }
+        }), r#"duplicate label "status""#);
+    }
+}
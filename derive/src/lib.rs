@@ -0,0 +1,95 @@
+//! Derive macros for [libcantal](https://docs.rs/libcantal)
+//!
+//! # Name
+//!
+//! `#[derive(Name)]` implements `libcantal::Name` for a struct with named
+//! fields, without allocating anything on visiting the name:
+//!
+//! ```rust,ignore
+//! #[derive(Name)]
+//! #[cantal(label(group = "http"))]
+//! struct Request {
+//!     #[cantal(rename = "metric")]
+//!     kind: &'static str,
+//!     status: u16,
+//! }
+//! ```
+//!
+//! Every field becomes a key of the name. Fields of types `&str`, `String`
+//! and `Cow<str>` are used as is, fields of other types are formatted
+//! with `Display` (so `Name::get` allocates a string for them). For fields
+//! of generic types, `Display` bound is added to the implementation.
+//!
+//! Attributes:
+//!
+//! * `#[cantal(label(key = "value", ...))]` on the struct -- adds constant
+//!   key-value pairs to the name
+//! * `#[cantal(rename = "key")]` on a field -- use another key instead of
+//!   the field name
+//! * `#[cantal(skip)]` on a field -- don't include this field in the name
+//!
+//! # Collection
+//!
+//! `#[derive(Collection)]` implements `libcantal::Collection` for a struct
+//! which fields are metric values (`Counter`, `Integer`, ...):
+//!
+//! ```rust,ignore
+//! #[derive(Collection)]
+//! #[cantal(label(group = "http"))]
+//! struct HttpMetrics {
+//!     requests: Counter,
+//!     #[cantal(label(status = "500"))]
+//!     errors: Counter,
+//!     #[cantal(rename = "in_flight")]
+//!     active: Integer,
+//!     #[cantal(nested)]
+//!     tls: TlsMetrics,
+//! }
+//! ```
+//!
+//! Every field becomes a metric named by the struct labels and the
+//! `metric` key set to the field name, i.e. the first field above is
+//! `{"group": "http", "metric": "requests"}`.
+//!
+//! Attributes:
+//!
+//! * `#[cantal(label(key = "value", ...))]` on the struct -- labels of
+//!   all the metrics in the struct (except `metric`, which is always set
+//!   from the field)
+//! * `#[cantal(label(key = "value", ...))]` on a field -- extra labels for
+//!   this metric, overriding struct labels with the same key
+//! * `#[cantal(rename = "name")]` on a field -- use another `metric` value
+//!   instead of the field name
+//! * `#[cantal(skip)]` on a field -- don't export this field
+//! * `#[cantal(nested)]` on a field -- the field is a collection by itself,
+//!   export all of its metrics as is
+extern crate proc_macro;
+extern crate proc_macro2;
+extern crate syn;
+#[macro_use] extern crate quote;
+
+mod attrs;
+mod collection;
+mod name;
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput};
+
+
+/// Implement `libcantal::Name` for a struct
+#[proc_macro_derive(Name, attributes(cantal))]
+pub fn derive_name(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    name::derive(input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+/// Implement `libcantal::Collection` for a struct of metrics
+#[proc_macro_derive(Collection, attributes(cantal))]
+pub fn derive_collection(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    collection::derive(input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
@@ -0,0 +1,209 @@
+use proc_macro2::{Ident, TokenStream, TokenTree};
+use syn::{Data, DeriveInput, Error, Fields, LitStr, Member, Result, Type};
+use syn::{GenericArgument, PathArguments};
+use syn::parse_quote;
+
+use attrs::Attrs;
+
+
+enum Value {
+    Const(LitStr),
+    Str(Member),
+    Display(Member),
+}
+
+struct Pair {
+    key: String,
+    value: Value,
+}
+
+fn is_str(ty: &Type) -> bool {
+    match *ty {
+        Type::Reference(ref r) => is_str(&r.elem),
+        Type::Group(ref g) => is_str(&g.elem),
+        Type::Paren(ref p) => is_str(&p.elem),
+        Type::Path(ref p) if p.qself.is_none() => {
+            match p.path.segments.last() {
+                Some(seg) if seg.ident == "Cow" => is_cow_str(&seg.arguments),
+                Some(seg) => seg.ident == "str" || seg.ident == "String",
+                None => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+// only `Cow<str>` is a string, i.e. `Cow<[u8]>` is formatted with `Display`
+fn is_cow_str(args: &PathArguments) -> bool {
+    match *args {
+        PathArguments::AngleBracketed(ref args) => {
+            args.args.iter().any(|arg| match *arg {
+                GenericArgument::Type(Type::Path(ref p)) => {
+                    p.qself.is_none() && p.path.is_ident("str")
+                }
+                _ => false,
+            })
+        }
+        _ => false,
+    }
+}
+
+fn uses_params(tokens: TokenStream, params: &[Ident]) -> bool {
+    tokens.into_iter().any(|tt| match tt {
+        TokenTree::Ident(ref ident) => params.contains(ident),
+        TokenTree::Group(ref group) => uses_params(group.stream(), params),
+        _ => false,
+    })
+}
+
+pub fn derive(input: DeriveInput) -> Result<TokenStream> {
+    let attrs = Attrs::parse(&input.attrs)?;
+    if attrs.rename.is_some() || attrs.skip || attrs.nested {
+        return Err(Error::new_spanned(&input.ident,
+            "only `label` is supported on the struct"));
+    }
+    let fields = match input.data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => &fields.named,
+            _ => return Err(Error::new_spanned(&input.ident,
+                "Name can only be derived for structs with named fields")),
+        },
+        _ => return Err(Error::new_spanned(&input.ident,
+            "Name can only be derived for structs")),
+    };
+
+    let params = input.generics.type_params()
+        .map(|p| p.ident.clone())
+        .collect::<Vec<_>>();
+    let mut generics = input.generics.clone();
+    let mut pairs = Vec::new();
+    for (key, value) in attrs.labels {
+        pairs.push(Pair { key, value: Value::Const(value) });
+    }
+    for field in fields {
+        let attrs = Attrs::parse(&field.attrs)?;
+        if !attrs.labels.is_empty() || attrs.nested {
+            return Err(Error::new_spanned(field,
+                "only `rename` and `skip` are supported on fields"));
+        }
+        if attrs.skip {
+            continue;
+        }
+        let ident = field.ident.clone().expect("named field");
+        let key = attrs.rename.map(|x| x.value())
+            .unwrap_or_else(|| ident.to_string());
+        let member = Member::Named(ident);
+        let value = if is_str(&field.ty) {
+            Value::Str(member)
+        } else {
+            let ty = &field.ty;
+            if uses_params(quote! { #ty }, &params) {
+                generics.make_where_clause().predicates
+                    .push(parse_quote! { #ty: ::std::fmt::Display });
+            }
+            Value::Display(member)
+        };
+        pairs.push(Pair { key, value });
+    }
+    // keys are sorted at compile time, so names are visited in
+    // the canonical order
+    pairs.sort_by(|a, b| a.key.cmp(&b.key));
+    for pair in pairs.windows(2) {
+        if pair[0].key == pair[1].key {
+            return Err(Error::new_spanned(&input.ident,
+                format!("duplicate key {:?} in name", pair[0].key)));
+        }
+    }
+
+    let mut get = Vec::new();
+    let mut visit = Vec::new();
+    for pair in &pairs {
+        let key = &pair.key;
+        match pair.value {
+            Value::Const(ref value) => {
+                get.push(quote! {
+                    #key => Some(::std::borrow::Cow::Borrowed(#value)),
+                });
+                visit.push(quote! { s.visit_pair(#key, #value); });
+            }
+            Value::Str(ref member) => {
+                get.push(quote! {
+                    #key => Some(::std::borrow::Cow::Borrowed(
+                        ::std::convert::AsRef::<str>::as_ref(&self.#member))),
+                });
+                visit.push(quote! {
+                    s.visit_pair(#key,
+                        ::std::convert::AsRef::<str>::as_ref(&self.#member));
+                });
+            }
+            Value::Display(ref member) => {
+                get.push(quote! {
+                    #key => Some(::std::borrow::Cow::Owned(
+                        ::std::string::ToString::to_string(&self.#member))),
+                });
+                visit.push(quote! {
+                    ::libcantal::visit_display(s, #key, &self.#member);
+                });
+            }
+        }
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) =
+        generics.split_for_impl();
+    // parenthesis are needed for `dyn ::path` to work on rust 2015
+    Ok(quote! {
+        impl #impl_generics ::libcantal::Name for #name #ty_generics
+            #where_clause
+        {
+            fn get(&self, key: &str)
+                -> Option<::std::borrow::Cow<'_, str>>
+            {
+                match key {
+                    #(#get)*
+                    _ => None,
+                }
+            }
+            fn visit(&self, s: &mut dyn (::libcantal::NameVisitor)) {
+                #(#visit)*
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use syn::parse_quote;
+
+    use super::derive;
+
+    fn error(input: ::syn::DeriveInput) -> String {
+        derive(input).err().expect("derive fails").to_string()
+    }
+
+    #[test]
+    fn duplicate_key() {
+        assert_eq!(error(parse_quote! {
+            #[cantal(label(metric = "requests"))]
+            struct Name { metric: &'static str }
+        }), r#"duplicate key "metric" in name"#);
+        assert_eq!(error(parse_quote! {
+            struct Name {
+                #[cantal(rename = "kind")]
+                metric: &'static str,
+                kind: u16,
+            }
+        }), r#"duplicate key "kind" in name"#);
+    }
+
+    #[test]
+    fn unsupported() {
+        assert_eq!(error(parse_quote! { struct Name(&'static str); }),
+            "Name can only be derived for structs with named fields");
+        assert_eq!(error(parse_quote! { enum Name { A } }),
+            "Name can only be derived for structs");
+        assert_eq!(error(parse_quote! {
+            struct Name { #[cantal(nested)] metric: &'static str }
+        }), "only `rename` and `skip` are supported on fields");
+    }
+}
@@ -0,0 +1,69 @@
+extern crate libcantal;
+#[macro_use] extern crate libcantal_derive;
+
+use libcantal::{Collection, Counter, Integer, Name, NameEncoder, Value};
+use libcantal::Visitor;
+
+
+struct Names(Vec<(String, String)>);
+
+impl<'a> Visitor<'a> for Names {
+    fn metric(&mut self, name: &dyn Name, value: &dyn Value) {
+        let mut buf = String::new();
+        NameEncoder::new().encode(name, &mut buf);
+        self.0.push((buf, value.as_json().to_string()));
+    }
+}
+
+fn metrics(coll: &dyn Collection) -> Vec<(String, String)> {
+    let mut names = Names(Vec::new());
+    coll.visit(&mut names);
+    names.0
+}
+
+#[derive(Collection)]
+#[cantal(label(group = "tls"))]
+struct TlsMetrics {
+    handshakes: Counter,
+}
+
+#[derive(Collection)]
+#[cantal(label(group = "http", status = "200"))]
+struct HttpMetrics {
+    requests: Counter,
+    #[cantal(label(status = "500"))]
+    errors: Counter,
+    #[cantal(rename = "in_flight")]
+    active: Integer,
+    #[cantal(skip)]
+    #[allow(dead_code)]
+    internal: Counter,
+    #[cantal(nested)]
+    tls: TlsMetrics,
+}
+
+#[test]
+fn derived() {
+    let http = HttpMetrics {
+        requests: Counter::new(),
+        errors: Counter::new(),
+        active: Integer::new(),
+        internal: Counter::new(),
+        tls: TlsMetrics { handshakes: Counter::new() },
+    };
+    http.requests.incr(3);
+    http.errors.incr(1);
+    http.active.set(2);
+    http.tls.handshakes.incr(5);
+    let pairs = |items: &[(&str, &str)]| items.iter()
+        .map(|&(n, v)| (n.to_string(), v.to_string()))
+        .collect::<Vec<_>>();
+    assert_eq!(metrics(&http), pairs(&[
+        (r#"{"group":"http","metric":"requests","status":"200"}"#, "3"),
+        // field label overrides struct label
+        (r#"{"group":"http","metric":"errors","status":"500"}"#, "1"),
+        (r#"{"group":"http","metric":"in_flight","status":"200"}"#, "2"),
+        // skipped field is not reported, nested one is reported as is
+        (r#"{"group":"tls","metric":"handshakes"}"#, "5"),
+    ]));
+}
@@ -0,0 +1,103 @@
+extern crate libcantal;
+#[macro_use] extern crate libcantal_derive;
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use libcantal::{Name, NameVisitor, NameEncoder};
+
+
+struct Pairs(BTreeMap<String, String>);
+
+impl NameVisitor for Pairs {
+    fn visit_pair(&mut self, key: &str, value: &str) {
+        assert!(self.0.insert(key.to_string(), value.to_string()).is_none(),
+                "duplicate key {:?}", key);
+    }
+}
+
+// checks that `get` returns the same values as `visit`
+fn pairs(name: &dyn Name) -> BTreeMap<String, String> {
+    let mut pairs = Pairs(BTreeMap::new());
+    name.visit(&mut pairs);
+    for (key, value) in &pairs.0 {
+        assert_eq!(name.get(key).as_deref(), Some(&value[..]), "key {}", key);
+    }
+    assert_eq!(name.get("no_such_key"), None);
+    pairs.0
+}
+
+fn encode(name: &dyn Name) -> String {
+    let mut buf = String::new();
+    NameEncoder::new().encode(name, &mut buf);
+    buf
+}
+
+#[derive(Name)]
+#[cantal(label(group = "http", version = "1"))]
+struct Request<'a> {
+    #[cantal(rename = "metric")]
+    kind: &'a str,
+    method: String,
+    path: Cow<'a, str>,
+    status: u16,
+    #[cantal(skip)]
+    _ignored: u64,
+}
+
+#[derive(Clone)]
+struct Bytes;
+
+impl fmt::Display for Bytes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("bytes")
+    }
+}
+
+#[derive(Name)]
+struct Generic<'a, T> {
+    metric: &'a str,
+    shard: T,
+}
+
+// only `Cow<str>` is used as is, other `Cow`s are formatted
+#[derive(Name)]
+struct NotStr<'a> {
+    metric: Cow<'a, Bytes>,
+}
+
+#[test]
+fn get_matches_visit() {
+    let req = Request {
+        kind: "requests",
+        method: "GET".to_string(),
+        path: Cow::Borrowed("/"),
+        status: 404,
+        _ignored: 1,
+    };
+    let pairs = pairs(&req);
+    assert_eq!(pairs.keys().collect::<Vec<_>>(),
+               ["group", "method", "metric", "path", "status", "version"]);
+    assert_eq!(req.get("metric").as_deref(), Some("requests"));
+    assert_eq!(req.get("status").as_deref(), Some("404"));
+    assert_eq!(req.get("kind"), None);
+    assert_eq!(req.get("_ignored"), None);
+    assert_eq!(encode(&req),
+        r#"{"group":"http","method":"GET","metric":"requests","path":"/","#
+        .to_string() + r#""status":"404","version":"1"}"#);
+}
+
+#[test]
+fn generic() {
+    let name = Generic { metric: "requests", shard: 7u8 };
+    pairs(&name);
+    assert_eq!(encode(&name), r#"{"metric":"requests","shard":"7"}"#);
+}
+
+#[test]
+fn cow_not_str() {
+    let name = NotStr { metric: Cow::Owned(Bytes) };
+    pairs(&name);
+    assert_eq!(encode(&name), r#"{"metric":"bytes"}"#);
+}
@@ -0,0 +1,33 @@
+extern crate libcantal;
+extern crate env_logger;
+
+use std::alloc::System;
+use std::env;
+use std::io::stdout;
+use std::time::Duration;
+use std::thread::sleep;
+
+use libcantal::{CountingAlloc, start, print};
+
+
+#[global_allocator]
+static ALLOC: CountingAlloc = CountingAlloc::new(System);
+
+
+fn main() {
+    if env::var("RUST_LOG").is_err() {
+        env::set_var("RUST_LOG", "warn");
+    }
+    env_logger::init().expect("init logging");
+
+    let _coll = start(&ALLOC).expect("cantal works");
+    let mut leak = Vec::new();
+    loop {
+        // some churn and some growth
+        let garbage = vec![0u8; 4096];
+        drop(garbage);
+        leak.push(String::from("leaked string"));
+        print(&ALLOC, stdout()).expect("can print");
+        sleep(Duration::new(1, 0));
+    }
+}
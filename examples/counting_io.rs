@@ -0,0 +1,63 @@
+extern crate libcantal;
+extern crate env_logger;
+
+#[macro_use] extern crate lazy_static;
+#[macro_use] extern crate serde_json;
+
+use std::env;
+use std::io::{self, stdout, BufRead, BufReader, Cursor};
+use std::sync::Arc;
+use std::time::Duration;
+use std::thread::sleep;
+
+use libcantal::{Counter, CountingReader, CountingWriter, Value};
+use libcantal::{start, print};
+
+
+lazy_static! {
+    static ref READ_BYTES: Counter = Counter::new();
+    static ref READ_OPS: Counter = Counter::new();
+}
+
+
+fn main() {
+    if env::var("RUST_LOG").is_err() {
+        env::set_var("RUST_LOG", "warn");
+    }
+    env_logger::init().expect("init logging");
+
+    let write_bytes = Arc::new(Counter::new());
+    let write_ops = Arc::new(Counter::new());
+    let write_errors = Arc::new(Counter::new());
+    let metrics = vec![
+        (json!({"metric": "read_bytes"}), &*READ_BYTES as &dyn Value),
+        (json!({"metric": "read_ops"}), &*READ_OPS),
+        (json!({"metric": "write_bytes"}), &*write_bytes),
+        (json!({"metric": "write_ops"}), &*write_ops),
+        (json!({"metric": "write_errors"}), &*write_errors),
+    ];
+    let _coll = start(&metrics).expect("cantal works");
+
+    let data = "hello\nworld\n".repeat(100);
+    loop {
+        // static counters
+        let mut input = BufReader::new(CountingReader::new(
+            Cursor::new(data.as_bytes()), &*READ_BYTES, &*READ_OPS));
+        let mut lines = 0;
+        let mut line = String::new();
+        while input.read_line(&mut line).expect("can read") > 0 {
+            lines += 1;
+            line.clear();
+        }
+        // shared counters
+        let mut output = CountingWriter::new(io::sink(),
+                write_bytes.clone(), write_ops.clone())
+            .errors(write_errors.clone());
+        io::copy(&mut Cursor::new(data.as_bytes()), &mut output)
+            .expect("can write");
+
+        println!("Read {} lines", lines);
+        print(&metrics, stdout()).expect("can print");
+        sleep(Duration::new(1, 0));
+    }
+}
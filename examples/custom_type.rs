@@ -3,6 +3,7 @@ extern crate env_logger;
 
 #[macro_use] extern crate lazy_static;
 
+use std::borrow::Cow;
 use std::env;
 use std::io::stdout;
 use std::time::Duration;
@@ -21,9 +22,9 @@ lazy_static! {
 struct Metric(&'static str);
 
 impl Name for Metric {
-    fn get(&self, key: &str) -> Option<&str> {
+    fn get(&self, key: &str) -> Option<Cow<'_, str>> {
         if key == "metric" {
-            return Some(self.0);
+            return Some(Cow::Borrowed(self.0));
         } else {
             return None;
         }
@@ -0,0 +1,63 @@
+extern crate libcantal;
+extern crate env_logger;
+
+#[macro_use] extern crate lazy_static;
+#[macro_use] extern crate libcantal_derive;
+
+use std::env;
+use std::io::stdout;
+use std::time::Duration;
+use std::thread::sleep;
+
+use libcantal::{Counter, Integer, start, print};
+
+
+#[derive(Collection)]
+#[cantal(label(group = "tls"))]
+struct TlsMetrics {
+    handshakes: Counter,
+}
+
+#[derive(Collection)]
+#[cantal(label(group = "http"))]
+struct HttpMetrics {
+    requests: Counter,
+    #[cantal(label(status = "500"))]
+    errors: Counter,
+    #[cantal(rename = "in_flight")]
+    active: Integer,
+    #[cantal(skip)]
+    #[allow(dead_code)]
+    not_exported: Counter,
+    #[cantal(nested)]
+    tls: TlsMetrics,
+}
+
+lazy_static! {
+    static ref METRICS: HttpMetrics = HttpMetrics {
+        requests: Counter::new(),
+        errors: Counter::new(),
+        active: Integer::new(),
+        not_exported: Counter::new(),
+        tls: TlsMetrics {
+            handshakes: Counter::new(),
+        },
+    };
+}
+
+fn main() {
+    if env::var("RUST_LOG").is_err() {
+        env::set_var("RUST_LOG", "warn");
+    }
+    env_logger::init().unwrap();
+
+    let _coll = start(&*METRICS).expect("cantal works");
+    loop {
+        METRICS.requests.incr(10);
+        METRICS.errors.incr(1);
+        METRICS.tls.handshakes.incr(2);
+        METRICS.active.set((METRICS.requests.get() % 7) as i64);
+        print(&*METRICS, stdout()).expect("can always print");
+        sleep(Duration::new(1, 0));
+    }
+}
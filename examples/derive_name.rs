@@ -0,0 +1,71 @@
+extern crate libcantal;
+extern crate env_logger;
+
+#[macro_use] extern crate lazy_static;
+#[macro_use] extern crate libcantal_derive;
+
+use std::env;
+use std::io::stdout;
+use std::time::Duration;
+use std::thread::sleep;
+
+use libcantal::{Collection, Counter, Integer, Value, start, print};
+
+
+lazy_static! {
+    static ref REQUESTS: Counter = Counter::new();
+    static ref ERRORS: Counter = Counter::new();
+    static ref IN_FLIGHT: Integer = Integer::new();
+}
+
+// Zero-allocating name, same as in `custom_type` example but derived
+#[derive(Name)]
+#[cantal(label(group = "example"))]
+struct Metric {
+    metric: &'static str,
+}
+
+// Non-string fields are formatted with `Display`
+#[derive(Name)]
+#[cantal(label(group = "example", metric = "requests"))]
+struct Requests {
+    #[cantal(rename = "status")]
+    status_code: u16,
+}
+
+fn gauges() -> Vec<(Metric, &'static dyn Value)> {
+    vec![
+        (Metric { metric: "in_flight" }, &*IN_FLIGHT),
+    ]
+}
+
+fn requests() -> Vec<(Requests, &'static dyn Value)> {
+    vec![
+        (Requests { status_code: 200 }, &*REQUESTS),
+        (Requests { status_code: 500 }, &*ERRORS),
+    ]
+}
+
+fn metrics() -> Box<dyn Collection> {
+    Box::new(vec![
+        Box::new(gauges()) as Box<dyn Collection>,
+        Box::new(requests()),
+    ])
+}
+
+fn main() {
+    if env::var("RUST_LOG").is_err() {
+        env::set_var("RUST_LOG", "warn");
+    }
+    env_logger::init().unwrap();
+
+    let met = metrics();
+    let _coll = start(&met).expect("cantal works");
+    loop {
+        REQUESTS.incr(7);
+        ERRORS.incr(1);
+        IN_FLIGHT.set((REQUESTS.get() % 13) as i64);
+        print(&met, stdout()).expect("can always print");
+        sleep(Duration::new(1, 0));
+    }
+}
@@ -0,0 +1,59 @@
+extern crate libcantal;
+#[macro_use] extern crate lazy_static;
+#[macro_use] extern crate serde_json;
+
+use std::collections::BTreeMap;
+use std::io::stdout;
+
+use serde_json::to_writer;
+
+use libcantal::{Collection, Counter, Integer, Json, NameVisitor};
+use libcantal::{RawType, Value, print};
+
+
+lazy_static! {
+    static ref REQUESTS: Counter = Counter::new();
+    static ref QUERIES: Counter = Counter::new();
+    static ref CONNECTIONS: Integer = Integer::new();
+}
+
+struct ToMap(BTreeMap<String, String>);
+
+impl NameVisitor for ToMap {
+    fn visit_pair(&mut self, key: &str, value: &str) {
+        self.0.insert(key.to_string(), value.to_string());
+    }
+}
+
+fn main() {
+    REQUESTS.incr(10);
+    QUERIES.incr(20);
+    CONNECTIONS.set(3);
+    let metrics = vec![
+        (json!({"group": "http", "metric": "requests"}),
+         &*REQUESTS as &dyn Value),
+        (json!({"group": "db", "metric": "queries"}), &*QUERIES),
+        (json!({"group": "db", "metric": "connections"}), &*CONNECTIONS),
+    ];
+
+    println!("Database metrics:");
+    let db = (&metrics).filter(|name, _| {
+        name.get("group").as_deref() == Some("db")
+    });
+    print(&db, stdout()).expect("can always print");
+
+    println!("Counters only:");
+    to_writer(stdout(),
+        &Json(&(&metrics).filter(|_, kind| kind == RawType::Counter)))
+        .expect("can always serialize");
+    println!();
+
+    println!("With an extra label:");
+    let renamed = (&metrics).map_names(|name| {
+        let mut map = ToMap(BTreeMap::new());
+        name.visit(&mut map);
+        map.0.insert("host".to_string(), "localhost".to_string());
+        map.0
+    });
+    print(&renamed, stdout()).expect("can always print");
+}
@@ -0,0 +1,29 @@
+extern crate libcantal;
+extern crate env_logger;
+
+use std::env;
+use std::io::stdout;
+use std::sync::Arc;
+use std::time::Duration;
+use std::thread::sleep;
+
+use libcantal::{HeartbeatMetrics, start, print, heartbeat};
+
+
+fn main() {
+    if env::var("RUST_LOG").is_err() {
+        env::set_var("RUST_LOG", "warn");
+    }
+    env_logger::init().expect("init logging");
+
+    let metrics = Arc::new(HeartbeatMetrics::new());
+    let _heartbeat = heartbeat(metrics.clone(), Duration::new(1, 0))
+        .expect("can start thread");
+    let _coll = start(&*metrics).expect("cantal works");
+    loop {
+        // heartbeat stops if main loop is stuck
+        metrics.touch();
+        print(&*metrics, stdout()).expect("can print");
+        sleep(Duration::new(1, 0));
+    }
+}
@@ -0,0 +1,52 @@
+#[macro_use] extern crate lazy_static;
+extern crate libcantal;
+extern crate env_logger;
+#[macro_use] extern crate serde_json;
+
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+use std::thread::sleep;
+
+use libcantal::{Counter, Integer, Value, History, start, record_history};
+
+
+lazy_static! {
+    static ref REQUESTS: Counter = Counter::new();
+    static ref CONNECTIONS: Integer = Integer::new();
+}
+
+
+fn main() {
+    if env::var("RUST_LOG").is_err() {
+        env::set_var("RUST_LOG", "warn");
+    }
+    env_logger::init().expect("init logging");
+
+    let metrics = Arc::new(vec![
+        (json!({"metric": "requests"}), &*REQUESTS as &dyn Value),
+        (json!({"metric": "connections"}), &*CONNECTIONS as &dyn Value),
+    ]);
+    let _coll = start(&*metrics).expect("cantal works");
+
+    // keep last minute of metrics, sampled every second
+    let history = Arc::new(History::new(60));
+    let _recorder = record_history(history.clone(), Duration::new(1, 0),
+                                   metrics.clone())
+        .expect("can start thread");
+
+    let requests: &[(&str, &str)] = &[("metric", "requests")];
+    let connections: &[(&str, &str)] = &[("metric", "connections")];
+    let window = Duration::new(10, 0);
+    for i in 0.. {
+        REQUESTS.incr(100);
+        CONNECTIONS.set(i % 7);
+        sleep(Duration::new(1, 0));
+        println!("requests: {:?}/s, connections: {:?}..{:?}",
+            history.rate(&requests, window),
+            history.min(&connections, window),
+            history.max(&connections, window));
+        println!("{}", serde_json::to_string(&history.last(&requests, 3))
+            .expect("can serialize"));
+    }
+}
@@ -0,0 +1,39 @@
+extern crate libcantal;
+extern crate env_logger;
+
+#[macro_use] extern crate lazy_static;
+#[macro_use] extern crate serde_json;
+
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+use std::thread::sleep;
+
+use libcantal::{Counter, Integer, Value, start, serve_http};
+
+
+lazy_static! {
+    static ref COUNTER: Counter = Counter::new();
+    static ref INTEGER: Integer = Integer::new();
+}
+
+fn main() {
+    if env::var("RUST_LOG").is_err() {
+        env::set_var("RUST_LOG", "warn");
+    }
+    env_logger::init().unwrap();
+
+    let metrics = Arc::new(vec![
+        (json!({"metric": "counter"}), &*COUNTER as &dyn Value),
+        (json!({"metric": "integer"}), &*INTEGER as &dyn Value),
+    ]);
+    let _coll = start(&*metrics).expect("cantal works");
+    let server = serve_http("127.0.0.1:0", metrics.clone())
+        .expect("http server starts");
+    println!("Try: curl http://{}/metrics", server.local_addr());
+    loop {
+        COUNTER.incr(1);
+        INTEGER.set((COUNTER.get() / 7) as i64);
+        sleep(Duration::new(1, 0));
+    }
+}
@@ -0,0 +1,60 @@
+extern crate libcantal;
+extern crate env_logger;
+
+#[macro_use] extern crate lazy_static;
+#[macro_use] extern crate serde_json;
+
+use std::env;
+use std::io::stdout;
+use std::time::Duration;
+use std::thread::sleep;
+
+use libcantal::{Collection, Counter, Integer, Labeled, Value, start, print};
+
+
+lazy_static! {
+    static ref REQUESTS: Counter = Counter::new();
+    static ref CONNECTIONS: Integer = Integer::new();
+    static ref QUERIES: Counter = Counter::new();
+}
+
+// Libraries export metrics without knowing where they are mounted
+fn http_library() -> Vec<(serde_json::Value, &'static dyn Value)> {
+    vec![
+        (json!({"metric": "requests"}), &*REQUESTS),
+        (json!({"metric": "connections"}), &*CONNECTIONS),
+    ]
+}
+
+fn db_library() -> Vec<(serde_json::Value, &'static dyn Value)> {
+    vec![
+        (json!({"group": "db", "metric": "queries"}), &*QUERIES),
+    ]
+}
+
+fn metrics() -> Box<dyn Collection> {
+    Box::new(vec![
+        Box::new(Labeled::new(http_library()).label("group", "http"))
+            as Box<dyn Collection>,
+        Box::new(Labeled::new(db_library())
+            .label("group", "storage")
+            .prefix("metric", "db.")),
+    ])
+}
+
+fn main() {
+    if env::var("RUST_LOG").is_err() {
+        env::set_var("RUST_LOG", "warn");
+    }
+    env_logger::init().unwrap();
+
+    let met = metrics();
+    let _coll = start(&met).expect("cantal works");
+    loop {
+        REQUESTS.incr(1);
+        QUERIES.incr(3);
+        CONNECTIONS.set((REQUESTS.get() % 7) as i64);
+        print(&met, stdout()).expect("can always print");
+        sleep(Duration::new(1, 0));
+    }
+}
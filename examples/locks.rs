@@ -0,0 +1,42 @@
+extern crate libcantal;
+extern crate env_logger;
+
+use std::env;
+use std::io::stdout;
+use std::sync::Arc;
+use std::time::Duration;
+use std::thread::{self, sleep};
+
+use libcantal::{Collection, InstrumentedMutex, InstrumentedRwLock};
+use libcantal::{start, print};
+
+
+fn main() {
+    if env::var("RUST_LOG").is_err() {
+        env::set_var("RUST_LOG", "warn");
+    }
+    env_logger::init().expect("init logging");
+
+    let queue = Arc::new(InstrumentedMutex::new("queue", Vec::new()));
+    let config = Arc::new(InstrumentedRwLock::new("config", 0));
+    let metrics: Vec<&dyn Collection> = vec![&*queue, &*config];
+    let _coll = start(&metrics).expect("cantal works");
+
+    for _ in 0..4 {
+        let queue = queue.clone();
+        let config = config.clone();
+        thread::spawn(move || loop {
+            let value = *config.read().expect("not poisoned");
+            let mut queue = queue.lock().expect("not poisoned");
+            queue.push(value);
+            // hold the lock for a while to make others wait
+            sleep(Duration::from_millis(10));
+        });
+    }
+    loop {
+        *config.write().expect("not poisoned") += 1;
+        queue.lock().expect("not poisoned").clear();
+        print(&metrics, stdout()).expect("can print");
+        sleep(Duration::new(1, 0));
+    }
+}
@@ -0,0 +1,47 @@
+extern crate libcantal;
+extern crate env_logger;
+#[macro_use] extern crate log;
+
+use std::env;
+use std::io::stdout;
+use std::sync::Arc;
+use std::time::Duration;
+use std::thread::sleep;
+
+use libcantal::{CountingLogger, LogMetrics, start, print};
+
+
+mod worker {
+    pub fn work(iteration: u64) {
+        if iteration % 3 == 2 {
+            warn!("Iteration {} is slow", iteration);
+        }
+    }
+}
+
+
+fn main() {
+    if env::var("RUST_LOG").is_err() {
+        env::set_var("RUST_LOG", "warn");
+    }
+    let metrics = Arc::new(LogMetrics::new().target("log::worker"));
+    let log_metrics = metrics.clone();
+    log::set_logger(|max_level| {
+        let mut builder = env_logger::LogBuilder::new();
+        builder.parse(&env::var("RUST_LOG").unwrap_or_default());
+        let logger = builder.build();
+        max_level.set(logger.filter());
+        Box::new(CountingLogger::new(logger, log_metrics))
+    }).expect("init logging");
+
+    let _coll = start(&*metrics).expect("cantal works");
+    for iteration in 0.. {
+        info!("Iteration {}", iteration);
+        worker::work(iteration);
+        if iteration % 5 == 4 {
+            error!("Iteration {} failed", iteration);
+        }
+        print(&*metrics, stdout()).expect("can print");
+        sleep(Duration::new(1, 0));
+    }
+}
@@ -0,0 +1,54 @@
+#[macro_use] extern crate libcantal;
+extern crate env_logger;
+
+use std::env;
+use std::io::stdout;
+use std::time::Duration;
+use std::thread::sleep;
+
+use libcantal::{Collection, Counter, Integer, start, print};
+
+
+mod http {
+    use libcantal::{Counter, Integer};
+
+    metrics! {
+        /// Metrics of the (imaginary) http server
+        pub fn metrics() {
+            /// Number of requests served
+            pub static REQUESTS: Counter =
+                {"group": "http", "metric": "requests"};
+            /// Number of currently open connections
+            pub(crate) static CONNECTIONS: Integer =
+                {"group": "http", "metric": "connections"};
+        }
+    }
+}
+
+metrics! {
+    fn main_metrics() {
+        static LOOPS: Counter = {"metric": "loops"};
+        static VALUE: Integer = {"metric": "value"};
+    }
+}
+
+
+fn main() {
+    if env::var("RUST_LOG").is_err() {
+        env::set_var("RUST_LOG", "warn");
+    }
+    env_logger::init().expect("init logging");
+
+    let http_metrics = http::metrics();
+    let main_metrics = main_metrics();
+    let metrics: Vec<&dyn Collection> = vec![&http_metrics, &main_metrics];
+    let _coll = start(&metrics).expect("cantal works");
+    loop {
+        LOOPS.incr(1);
+        VALUE.set((LOOPS.get() % 7) as i64);
+        http::REQUESTS.incr(3);
+        http::CONNECTIONS.set((LOOPS.get() % 3) as i64);
+        print(&metrics, stdout()).expect("can print");
+        sleep(Duration::new(1, 0));
+    }
+}
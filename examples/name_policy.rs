@@ -0,0 +1,45 @@
+extern crate libcantal;
+extern crate env_logger;
+
+#[macro_use] extern crate serde_json;
+
+use std::env;
+use std::io::stdout;
+
+use libcantal::{Counter, Coerce, Strict, Value, start, print};
+
+
+fn main() {
+    if env::var("RUST_LOG").is_err() {
+        env::set_var("RUST_LOG", "warn");
+    }
+    env_logger::init().expect("init logging");
+
+    let shard0 = Counter::new();
+    let shard1 = Counter::new();
+    let metrics = vec![
+        (json!({"metric": "requests", "shard": 0,
+                "http": {"method": "GET"}}), &shard0 as &dyn Value),
+        (json!({"metric": "requests", "shard": 1,
+                "http": {"method": "GET"}}), &shard1 as &dyn Value),
+    ];
+
+    // by default numbers are skipped, so both metrics are named
+    // {"http.method": "GET", "metric": "requests"}
+    print(&metrics, stdout()).expect("can print");
+
+    let strict = metrics.iter()
+        .map(|&(ref name, value)| (Strict(name), value))
+        .collect::<Vec<_>>();
+    match start(&strict) {
+        Ok(_) => println!("Strict: started"),
+        Err(e) => println!("Strict: {}", e),
+    }
+
+    // numbers are converted to strings
+    let coerce = metrics.iter()
+        .map(|&(ref name, value)| (Coerce(name), value))
+        .collect::<Vec<_>>();
+    print(&coerce, stdout()).expect("can print");
+    let _coll = start(&coerce).expect("cantal works");
+}
@@ -0,0 +1,38 @@
+extern crate libcantal;
+#[macro_use] extern crate lazy_static;
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::stdout;
+
+use libcantal::{Collection, Counter, Name, Value, print};
+
+
+lazy_static! {
+    static ref REQUESTS: Counter = Counter::new();
+    static ref ERRORS: Counter = Counter::new();
+    static ref TIMEOUTS: Counter = Counter::new();
+    static ref RETRIES: Counter = Counter::new();
+}
+
+fn main() {
+    let mut btree = BTreeMap::new();
+    btree.insert("group".to_string(), "example".to_string());
+    btree.insert("metric".to_string(), "requests".to_string());
+    let mut hash = HashMap::new();
+    hash.insert("metric", "errors");
+    hash.insert("group", "example");
+
+    let metrics: Vec<Box<dyn Collection>> = vec![
+        Box::new(vec![(btree, &*REQUESTS as &dyn Value)]),
+        Box::new(vec![(hash, &*ERRORS as &dyn Value)]),
+        Box::new(vec![
+            (&[("group", "example"), ("metric", "timeouts")][..],
+             &*TIMEOUTS as &dyn Value),
+        ]),
+        Box::new(vec![(("metric", "retries"), &*RETRIES as &dyn Value)]),
+    ];
+    REQUESTS.incr(10);
+    ERRORS.incr(1);
+    print(&metrics, stdout()).expect("can always print");
+    println!("{:?}", ("metric", "retries").get("metric"));
+}
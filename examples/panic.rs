@@ -0,0 +1,33 @@
+extern crate libcantal;
+extern crate env_logger;
+
+use std::env;
+use std::io::stdout;
+use std::time::Duration;
+use std::thread::{self, sleep};
+
+use libcantal::{install_panic_hook, start, print};
+
+
+fn main() {
+    if env::var("RUST_LOG").is_err() {
+        env::set_var("RUST_LOG", "warn");
+    }
+    env_logger::init().expect("init logging");
+
+    let metrics = install_panic_hook();
+    let _coll = start(metrics).expect("cantal works");
+    for iteration in 0.. {
+        thread::Builder::new()
+            .name(format!("worker-{}", iteration))
+            .spawn(move || {
+                if iteration % 2 == 1 {
+                    panic!("odd iteration");
+                }
+            })
+            .expect("can spawn thread")
+            .join().ok();
+        print(metrics, stdout()).expect("can print");
+        sleep(Duration::new(1, 0));
+    }
+}
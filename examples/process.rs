@@ -0,0 +1,30 @@
+extern crate libcantal;
+extern crate env_logger;
+
+use std::env;
+use std::io::stdout;
+use std::sync::Arc;
+use std::time::Duration;
+use std::thread::sleep;
+
+use libcantal::{ProcessMetrics, start, print, refresh_process};
+
+
+fn main() {
+    if env::var("RUST_LOG").is_err() {
+        env::set_var("RUST_LOG", "warn");
+    }
+    env_logger::init().expect("init logging");
+
+    let metrics = Arc::new(ProcessMetrics::new());
+    let _refresher = refresh_process(metrics.clone(), Duration::new(1, 0))
+        .expect("can start thread");
+    let _coll = start(&*metrics).expect("cantal works");
+    let mut garbage = Vec::new();
+    loop {
+        // allocate some memory to see how rss grows
+        garbage.push(vec![1u8; 1 << 20]);
+        print(&*metrics, stdout()).expect("can print");
+        sleep(Duration::new(1, 0));
+    }
+}
@@ -0,0 +1,38 @@
+extern crate libcantal;
+extern crate env_logger;
+
+use std::env;
+use std::io::stdout;
+use std::sync::Arc;
+use std::time::Duration;
+use std::thread::{self, sleep};
+
+use libcantal::{QueueMetrics, counting_sync_channel, start, print};
+
+
+fn main() {
+    if env::var("RUST_LOG").is_err() {
+        env::set_var("RUST_LOG", "warn");
+    }
+    env_logger::init().expect("init logging");
+
+    let metrics = Arc::new(QueueMetrics::new("jobs"));
+    let (tx, rx) = counting_sync_channel(10, metrics.clone());
+    let _coll = start(&*metrics).expect("cantal works");
+
+    // slow consumer
+    thread::spawn(move || {
+        for job in &rx {
+            let _: u64 = job;
+            sleep(Duration::from_millis(300));
+        }
+    });
+    for job in 0.. {
+        // producer is faster, so the queue fills up and jobs are dropped
+        tx.try_send(job).ok();
+        if job % 4 == 3 {
+            print(&*metrics, stdout()).expect("can print");
+        }
+        sleep(Duration::from_millis(250));
+    }
+}
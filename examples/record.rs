@@ -0,0 +1,40 @@
+extern crate libcantal;
+extern crate env_logger;
+
+#[macro_use] extern crate lazy_static;
+#[macro_use] extern crate serde_json;
+
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+use std::thread::sleep;
+
+use libcantal::{Counter, Integer, Value, record_rotating};
+
+
+lazy_static! {
+    static ref COUNTER: Counter = Counter::new();
+    static ref INTEGER: Integer = Integer::new();
+}
+
+fn main() {
+    if env::var("RUST_LOG").is_err() {
+        env::set_var("RUST_LOG", "warn");
+    }
+    env_logger::init().unwrap();
+
+    let path = env::args().nth(1).unwrap_or("metrics.jsonl".into());
+    let metrics = Arc::new(vec![
+        (json!({"metric": "counter"}), &*COUNTER as &dyn Value),
+        (json!({"metric": "integer"}), &*INTEGER as &dyn Value),
+    ]);
+    let _recorder = record_rotating(&path, Duration::new(1, 0),
+        1 << 20, 3, metrics)
+        .expect("recorder starts");
+    println!("Recording metrics to {:?}", path);
+    loop {
+        COUNTER.incr(1);
+        INTEGER.set((COUNTER.get() / 7) as i64);
+        sleep(Duration::from_millis(100));
+    }
+}
@@ -0,0 +1,53 @@
+#[macro_use] extern crate libcantal;
+extern crate env_logger;
+
+use std::env;
+use std::io::stdout;
+use std::time::Duration;
+use std::thread::sleep;
+
+use libcantal::{Counter, print, register, registry, start_registry};
+
+
+// A library registers its metrics when initialized
+mod http {
+    use libcantal::{Counter, register};
+
+    metrics! {
+        fn metrics() {
+            pub static REQUESTS: Counter = {"metric": "requests"};
+        }
+    }
+
+    pub fn init() {
+        // collection is created once and lives till the end of the program
+        register("http", Box::leak(Box::new(metrics())));
+    }
+}
+
+metrics! {
+    fn main_metrics() {
+        static LOOPS: Counter = {"metric": "loops"};
+    }
+}
+
+
+fn main() {
+    if env::var("RUST_LOG").is_err() {
+        env::set_var("RUST_LOG", "warn");
+    }
+    env_logger::init().expect("init logging");
+
+    register("main", Box::leak(Box::new(main_metrics())));
+    let _guard = start_registry().expect("cantal works");
+    loop {
+        LOOPS.incr(1);
+        if LOOPS.get() == 3 {
+            // registering after start re-creates the file with new metrics
+            http::init();
+        }
+        http::REQUESTS.incr(2);
+        print(registry(), stdout()).expect("can print");
+        sleep(Duration::new(1, 0));
+    }
+}
@@ -0,0 +1,34 @@
+extern crate libcantal;
+#[macro_use] extern crate lazy_static;
+#[macro_use] extern crate serde_json;
+
+use std::io::stdout;
+use std::time::{Duration, SystemTime};
+use std::thread::sleep;
+
+use libcantal::{Counter, Integer, Value, graphite, influx};
+
+
+lazy_static! {
+    static ref COUNTER: Counter = Counter::new();
+    static ref INTEGER: Integer = Integer::new();
+}
+
+fn main() {
+    let metrics = [
+        (json!({"group": "example", "metric": "counter"}),
+         &*COUNTER as &dyn Value),
+        (json!({"group": "example", "metric": "integer", "kind": "x"}),
+         &*INTEGER as &dyn Value),
+    ];
+    loop {
+        COUNTER.incr(1);
+        INTEGER.set((COUNTER.get() / 7) as i64);
+        let now = SystemTime::now();
+        graphite(&metrics[..], &["group", "metric"], now, stdout())
+            .expect("can always print");
+        influx(&metrics[..], "group", now, stdout())
+            .expect("can always print");
+        sleep(Duration::new(1, 0));
+    }
+}
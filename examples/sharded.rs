@@ -0,0 +1,59 @@
+extern crate libcantal;
+extern crate env_logger;
+
+#[macro_use] extern crate lazy_static;
+#[macro_use] extern crate serde_json;
+
+use std::env;
+use std::io::stdout;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use std::thread::{self, sleep};
+
+use libcantal::{Counter, ShardedCounter, Value};
+use libcantal::{start, print, flush_sharded};
+
+
+lazy_static! {
+    static ref SHARDED: ShardedCounter = ShardedCounter::new();
+    static ref PLAIN: Counter = Counter::new();
+}
+
+const THREADS: usize = 8;
+const ITERATIONS: u64 = 10_000_000;
+
+fn bench<F: Fn() + Send + Sync + 'static>(title: &str, f: F) {
+    let f = Arc::new(f);
+    let start = Instant::now();
+    let threads = (0..THREADS).map(|_| {
+        let f = f.clone();
+        thread::spawn(move || for _ in 0..ITERATIONS { f() })
+    }).collect::<Vec<_>>();
+    for t in threads {
+        t.join().expect("thread works");
+    }
+    println!("{}: {:?}", title, start.elapsed());
+}
+
+fn main() {
+    if env::var("RUST_LOG").is_err() {
+        env::set_var("RUST_LOG", "warn");
+    }
+    env_logger::init().expect("init logging");
+
+    let metrics = Arc::new(vec![
+        (json!({"metric": "sharded"}), &*SHARDED as &dyn Value),
+        (json!({"metric": "plain"}), &*PLAIN),
+    ]);
+    let _coll = start(&*metrics).expect("cantal works");
+    // sharded counters are written to the file only when flushed
+    let _flusher = flush_sharded(Duration::new(1, 0), metrics.clone())
+        .expect("can start thread");
+
+    bench("plain", || PLAIN.incr(1));
+    bench("sharded", || SHARDED.incr(1));
+    loop {
+        print(&*metrics, stdout()).expect("can print");
+        sleep(Duration::new(1, 0));
+    }
+}
@@ -0,0 +1,30 @@
+extern crate libcantal;
+#[macro_use] extern crate lazy_static;
+#[macro_use] extern crate serde_json;
+
+use serde_json::{to_string, from_str};
+
+use libcantal::{Counter, Integer, Value, Json, Snapshot};
+
+
+lazy_static! {
+    static ref COUNTER: Counter = Counter::new();
+    static ref INTEGER: Integer = Integer::new();
+}
+
+fn main() {
+    COUNTER.incr(10);
+    INTEGER.set(-3);
+    let metrics = [
+        (json!({"metric": "counter"}), &*COUNTER as &dyn Value),
+        (json!({"metric": "integer"}), &*INTEGER as &dyn Value),
+    ];
+    let dump = to_string(&Json(&metrics[..])).expect("can serialize");
+    println!("Dump: {}", dump);
+    let snapshot: Snapshot = from_str(&dump).expect("can deserialize");
+    for metric in &snapshot.metrics {
+        println!("{:?} {:?}", metric.name, metric.value);
+    }
+    println!("Counter: {:?}",
+        snapshot.get(&json!({"metric": "counter"})));
+}
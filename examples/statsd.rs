@@ -0,0 +1,51 @@
+extern crate libcantal;
+extern crate env_logger;
+
+#[macro_use] extern crate lazy_static;
+#[macro_use] extern crate serde_json;
+
+use std::env;
+use std::net::UdpSocket;
+use std::sync::Arc;
+use std::str::from_utf8;
+use std::time::Duration;
+
+use libcantal::{Counter, Integer, Value, push_statsd};
+
+
+lazy_static! {
+    static ref COUNTER: Counter = Counter::new();
+    static ref INTEGER: Integer = Integer::new();
+}
+
+fn main() {
+    if env::var("RUST_LOG").is_err() {
+        env::set_var("RUST_LOG", "warn");
+    }
+    env_logger::init().unwrap();
+
+    // A local socket acting as a statsd server
+    let server = UdpSocket::bind("127.0.0.1:0").expect("bind works");
+    let metrics = Arc::new(vec![
+        (json!({"group": "example", "metric": "counter"}),
+         &*COUNTER as &dyn Value),
+        (json!({"group": "example", "metric": "integer"}),
+         &*INTEGER as &dyn Value),
+    ]);
+    let pusher = push_statsd(server.local_addr().unwrap(),
+        Duration::from_millis(100), metrics)
+        .expect("statsd pusher starts");
+
+    let mut buf = [0u8; 2048];
+    for _ in 0..5 {
+        COUNTER.incr(3);
+        INTEGER.set(7 - (COUNTER.get() as i64));
+        let bytes = server.recv(&mut buf).expect("can receive packet");
+        println!("{}\n", from_utf8(&buf[..bytes]).expect("utf-8 packet"));
+    }
+    COUNTER.incr(100);
+    // Final values are flushed when pusher is dropped
+    drop(pusher);
+    let bytes = server.recv(&mut buf).expect("can receive packet");
+    println!("{}", from_utf8(&buf[..bytes]).expect("utf-8 packet"));
+}
@@ -0,0 +1,69 @@
+#[macro_use] extern crate lazy_static;
+extern crate libcantal;
+extern crate env_logger;
+#[macro_use] extern crate serde_json;
+
+use std::env;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use std::thread::sleep;
+
+use libcantal::{Counter, Integer, Value, Watcher, Rule, start, watch};
+
+
+lazy_static! {
+    static ref QUEUE_SIZE: Integer = Integer::new();
+    static ref PROCESSED: Counter = Counter::new();
+    static ref SHEDDING: AtomicBool = AtomicBool::new(false);
+}
+
+
+fn main() {
+    if env::var("RUST_LOG").is_err() {
+        env::set_var("RUST_LOG", "warn");
+    }
+    env_logger::init().expect("init logging");
+
+    let metrics = Arc::new(vec![
+        (json!({"metric": "queue_size"}), &*QUEUE_SIZE as &dyn Value),
+        (json!({"metric": "processed"}), &*PROCESSED as &dyn Value),
+    ]);
+    let _coll = start(&*metrics).expect("cantal works");
+
+    let queue_size: &[(&str, &str)] = &[("metric", "queue_size")];
+    let processed: &[(&str, &str)] = &[("metric", "processed")];
+    let watcher = Watcher::new()
+        .rule(Rule::above(&queue_size, 100., 50.)
+            .on_enter(|size| {
+                println!("queue is {}, shedding load", size);
+                SHEDDING.store(true, Ordering::SeqCst);
+            })
+            .on_exit(|size| {
+                println!("queue is {}, accepting requests", size);
+                SHEDDING.store(false, Ordering::SeqCst);
+            }))
+        .rule(Rule::stale(&processed, Duration::new(3, 0))
+            .on_enter(|secs| println!("nothing processed for {}s", secs))
+            .on_exit(|_| println!("processing again")));
+    let _watcher = watch(watcher, Duration::from_millis(500), metrics.clone())
+        .expect("can start thread");
+
+    for i in 0u64.. {
+        // fill the queue for a while, then get stuck, then drain it
+        match (i / 10) % 3 {
+            0 => {
+                if !SHEDDING.load(Ordering::SeqCst) {
+                    QUEUE_SIZE.incr(20);
+                }
+                PROCESSED.incr(1);
+            }
+            1 => {}
+            _ => {
+                QUEUE_SIZE.set((QUEUE_SIZE.get() - 20).max(0));
+                PROCESSED.incr(20);
+            }
+        }
+        sleep(Duration::from_millis(500));
+    }
+}
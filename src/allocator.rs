@@ -0,0 +1,161 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+
+use collection::{Collection, Visitor, visit_group};
+use counter::Counter;
+use integer::Integer;
+
+
+const UNINITIALIZED: usize = 0;
+const INITIALIZING: usize = 1;
+const INITIALIZED: usize = 2;
+
+/// Allocation metrics updated by `CountingAlloc`
+///
+/// Named `{"group": "alloc", "metric": ..}`:
+///
+/// * `allocations`, `deallocations`, `reallocations` -- number of calls
+/// * `allocated_bytes`, `freed_bytes` -- total bytes (reallocation counts
+///   as freeing old block and allocating a new one)
+/// * `live_bytes` -- bytes currently allocated (a level)
+pub struct AllocMetrics {
+    allocations: Counter,
+    deallocations: Counter,
+    reallocations: Counter,
+    allocated_bytes: Counter,
+    freed_bytes: Counter,
+    live_bytes: Integer,
+}
+
+/// A global allocator that counts allocations
+///
+/// ```rust,ignore
+/// #[global_allocator]
+/// static ALLOC: CountingAlloc = CountingAlloc::new(System);
+///
+/// let _coll = start(&ALLOC).expect("cantal works");
+/// ```
+///
+/// Metrics are created on the first allocation (so allocations made while
+/// creating them aren't counted), and are exported as `AllocMetrics`.
+pub struct CountingAlloc<A = System> {
+    inner: A,
+    state: AtomicUsize,
+    metrics: AtomicPtr<AllocMetrics>,
+}
+
+impl<A> CountingAlloc<A> {
+    /// Wrap an allocator
+    pub const fn new(inner: A) -> CountingAlloc<A> {
+        CountingAlloc {
+            inner,
+            state: AtomicUsize::new(UNINITIALIZED),
+            metrics: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+    /// Returns allocation metrics, creating them if needed
+    pub fn metrics(&self) -> &'static AllocMetrics {
+        loop {
+            if let Some(metrics) = self.try_metrics() {
+                return metrics;
+            }
+            // another thread is creating metrics right now
+            ::std::thread::yield_now();
+        }
+    }
+    // Returns None if metrics are being created, either by this thread,
+    // (then we are called recursively for allocating them) or by another
+    fn try_metrics(&self) -> Option<&'static AllocMetrics> {
+        // fast path, this is called on every allocation
+        if self.state.load(Ordering::Acquire) == INITIALIZED {
+            return Some(unsafe { &*self.metrics.load(Ordering::Acquire) });
+        }
+        match self.state.compare_exchange(UNINITIALIZED, INITIALIZING,
+            Ordering::SeqCst, Ordering::SeqCst)
+        {
+            Ok(_) => {
+                let metrics = Box::into_raw(Box::new(AllocMetrics {
+                    allocations: Counter::new(),
+                    deallocations: Counter::new(),
+                    reallocations: Counter::new(),
+                    allocated_bytes: Counter::new(),
+                    freed_bytes: Counter::new(),
+                    live_bytes: Integer::new(),
+                }));
+                self.metrics.store(metrics, Ordering::SeqCst);
+                self.state.store(INITIALIZED, Ordering::SeqCst);
+                Some(unsafe { &*metrics })
+            }
+            Err(INITIALIZED) => {
+                Some(unsafe { &*self.metrics.load(Ordering::Acquire) })
+            }
+            Err(_) => None,
+        }
+    }
+    fn allocated(&self, size: usize) {
+        if let Some(m) = self.try_metrics() {
+            m.allocations.incr(1);
+            m.allocated_bytes.incr(size as u64);
+            m.live_bytes.incr(size as i64);
+        }
+    }
+}
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for CountingAlloc<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.alloc(layout);
+        if !ptr.is_null() {
+            self.allocated(layout.size());
+        }
+        ptr
+    }
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            self.allocated(layout.size());
+        }
+        ptr
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.inner.dealloc(ptr, layout);
+        if let Some(m) = self.try_metrics() {
+            m.deallocations.incr(1);
+            m.freed_bytes.incr(layout.size() as u64);
+            m.live_bytes.decr(layout.size() as i64);
+        }
+    }
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize)
+        -> *mut u8
+    {
+        let new_ptr = self.inner.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            if let Some(m) = self.try_metrics() {
+                m.reallocations.incr(1);
+                m.freed_bytes.incr(layout.size() as u64);
+                m.allocated_bytes.incr(new_size as u64);
+                m.live_bytes.incr(new_size as i64 - layout.size() as i64);
+            }
+        }
+        new_ptr
+    }
+}
+
+impl Collection for AllocMetrics {
+    fn visit<'x>(&'x self, visitor: &mut dyn Visitor<'x>) {
+        visit_group(visitor, "alloc", None, &[
+            ("allocations", &self.allocations),
+            ("deallocations", &self.deallocations),
+            ("reallocations", &self.reallocations),
+            ("allocated_bytes", &self.allocated_bytes),
+            ("freed_bytes", &self.freed_bytes),
+            ("live_bytes", &self.live_bytes),
+        ]);
+    }
+}
+
+impl<A> Collection for CountingAlloc<A> {
+    fn visit<'x>(&'x self, visitor: &mut dyn Visitor<'x>) {
+        self.metrics().visit(visitor)
+    }
+}
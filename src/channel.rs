@@ -0,0 +1,293 @@
+use std::sync::Arc;
+use std::sync::mpsc::{self, SendError, TrySendError};
+use std::sync::mpsc::{RecvError, TryRecvError, RecvTimeoutError};
+use std::time::Duration;
+
+use collection::{Collection, Visitor, visit_group};
+use counter::Counter;
+use integer::Integer;
+
+
+/// Metrics of a queue, updated by `counting_channel` wrappers
+///
+/// Named `{"group": "queue", "queue": <name>, "metric": ..}` where metric
+/// is one of:
+///
+/// * `in_flight` -- number of items in the queue (a level)
+/// * `sent`, `received` -- number of items passed through the queue
+/// * `dropped` -- items that weren't sent because receiver is gone or
+///   the queue is full, and items left in the queue when receiver is dropped
+pub struct QueueMetrics {
+    name: String,
+    in_flight: Integer,
+    sent: Counter,
+    received: Counter,
+    dropped: Counter,
+}
+
+// An item in the queue, counts itself as dropped unless received, so
+// items discarded by the channel when receiver is gone are never missed
+struct Tracked<T> {
+    value: Option<T>,
+    metrics: Arc<QueueMetrics>,
+}
+
+/// A sender that updates `QueueMetrics`, see `counting_channel`
+pub struct CountingSender<T> {
+    inner: mpsc::Sender<Tracked<T>>,
+    metrics: Arc<QueueMetrics>,
+}
+
+/// A sender that updates `QueueMetrics`, see `counting_sync_channel`
+pub struct CountingSyncSender<T> {
+    inner: mpsc::SyncSender<Tracked<T>>,
+    metrics: Arc<QueueMetrics>,
+}
+
+/// A receiver that updates `QueueMetrics`
+pub struct CountingReceiver<T> {
+    inner: mpsc::Receiver<Tracked<T>>,
+    metrics: Arc<QueueMetrics>,
+}
+
+/// Iterator over messages of `CountingReceiver`, see `iter`
+pub struct CountingIter<'a, T: 'a> {
+    receiver: &'a CountingReceiver<T>,
+}
+
+/// Create an unbounded channel that updates `metrics`
+///
+/// ```rust
+/// # extern crate libcantal;
+/// # use std::sync::Arc;
+/// # use libcantal::{QueueMetrics, counting_channel, start};
+/// # fn main() {
+/// let metrics = Arc::new(QueueMetrics::new("jobs"));
+/// let (tx, rx) = counting_channel(metrics.clone());
+/// let _coll = start(&*metrics).expect("cantal works");
+/// tx.send("job").unwrap();
+/// assert_eq!(metrics.in_flight(), 1);
+/// # drop(rx);
+/// # }
+/// ```
+pub fn counting_channel<T>(metrics: Arc<QueueMetrics>)
+    -> (CountingSender<T>, CountingReceiver<T>)
+{
+    let (tx, rx) = mpsc::channel();
+    (CountingSender { inner: tx, metrics: metrics.clone() },
+     CountingReceiver { inner: rx, metrics })
+}
+
+/// Create a bounded channel that updates `metrics`
+pub fn counting_sync_channel<T>(bound: usize, metrics: Arc<QueueMetrics>)
+    -> (CountingSyncSender<T>, CountingReceiver<T>)
+{
+    let (tx, rx) = mpsc::sync_channel(bound);
+    (CountingSyncSender { inner: tx, metrics: metrics.clone() },
+     CountingReceiver { inner: rx, metrics })
+}
+
+impl QueueMetrics {
+    /// Create metrics for a queue named `name`
+    pub fn new<S: Into<String>>(name: S) -> QueueMetrics {
+        QueueMetrics {
+            name: name.into(),
+            in_flight: Integer::new(),
+            sent: Counter::new(),
+            received: Counter::new(),
+            dropped: Counter::new(),
+        }
+    }
+    /// Returns number of items currently in the queue
+    pub fn in_flight(&self) -> i64 {
+        self.in_flight.get()
+    }
+    /// Returns number of items sent
+    pub fn sent(&self) -> u64 {
+        self.sent.get()
+    }
+    /// Returns number of items received
+    pub fn received(&self) -> u64 {
+        self.received.get()
+    }
+    /// Returns number of items dropped
+    pub fn dropped(&self) -> u64 {
+        self.dropped.get()
+    }
+    // in_flight is incremented before sending, so it's never negative
+    fn before_send(&self) {
+        self.in_flight.incr(1);
+    }
+    fn after_send(&self, ok: bool) {
+        if ok {
+            self.sent.incr(1);
+        } else {
+            self.in_flight.decr(1);
+            self.dropped.incr(1);
+        }
+    }
+    fn on_receive(&self) {
+        self.in_flight.decr(1);
+        self.received.incr(1);
+    }
+}
+
+impl<T> Tracked<T> {
+    fn new(value: T, metrics: &Arc<QueueMetrics>) -> Tracked<T> {
+        Tracked { value: Some(value), metrics: metrics.clone() }
+    }
+    // the caller is responsible for updating metrics
+    fn into_inner(mut self) -> T {
+        self.value.take().expect("value is taken only once")
+    }
+}
+
+impl<T> Drop for Tracked<T> {
+    fn drop(&mut self) {
+        if self.value.is_some() {
+            self.metrics.in_flight.decr(1);
+            self.metrics.dropped.incr(1);
+        }
+    }
+}
+
+impl<T> CountingSender<T> {
+    /// Send a value, see `std::sync::mpsc::Sender::send`
+    pub fn send(&self, t: T) -> Result<(), SendError<T>> {
+        self.metrics.before_send();
+        let result = self.inner.send(Tracked::new(t, &self.metrics));
+        self.metrics.after_send(result.is_ok());
+        result.map_err(|SendError(t)| SendError(t.into_inner()))
+    }
+}
+
+impl<T> Clone for CountingSender<T> {
+    fn clone(&self) -> CountingSender<T> {
+        CountingSender {
+            inner: self.inner.clone(),
+            metrics: self.metrics.clone(),
+        }
+    }
+}
+
+impl<T> CountingSyncSender<T> {
+    /// Send a value, see `std::sync::mpsc::SyncSender::send`
+    pub fn send(&self, t: T) -> Result<(), SendError<T>> {
+        self.metrics.before_send();
+        let result = self.inner.send(Tracked::new(t, &self.metrics));
+        self.metrics.after_send(result.is_ok());
+        result.map_err(|SendError(t)| SendError(t.into_inner()))
+    }
+    /// Try to send a value, see `std::sync::mpsc::SyncSender::try_send`
+    ///
+    /// Values not sent because the queue is full are counted as dropped.
+    pub fn try_send(&self, t: T) -> Result<(), TrySendError<T>> {
+        self.metrics.before_send();
+        let result = self.inner.try_send(Tracked::new(t, &self.metrics));
+        self.metrics.after_send(result.is_ok());
+        result.map_err(|e| match e {
+            TrySendError::Full(t) => TrySendError::Full(t.into_inner()),
+            TrySendError::Disconnected(t) => {
+                TrySendError::Disconnected(t.into_inner())
+            }
+        })
+    }
+}
+
+impl<T> Clone for CountingSyncSender<T> {
+    fn clone(&self) -> CountingSyncSender<T> {
+        CountingSyncSender {
+            inner: self.inner.clone(),
+            metrics: self.metrics.clone(),
+        }
+    }
+}
+
+impl<T> CountingReceiver<T> {
+    fn received<E>(&self, result: Result<Tracked<T>, E>) -> Result<T, E> {
+        if result.is_ok() {
+            self.metrics.on_receive();
+        }
+        result.map(Tracked::into_inner)
+    }
+    /// Receive a value, see `std::sync::mpsc::Receiver::recv`
+    pub fn recv(&self) -> Result<T, RecvError> {
+        self.received(self.inner.recv())
+    }
+    /// Receive a value, see `std::sync::mpsc::Receiver::try_recv`
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        self.received(self.inner.try_recv())
+    }
+    /// Receive a value, see `std::sync::mpsc::Receiver::recv_timeout`
+    pub fn recv_timeout(&self, timeout: Duration)
+        -> Result<T, RecvTimeoutError>
+    {
+        self.received(self.inner.recv_timeout(timeout))
+    }
+    /// Returns an iterator that blocks waiting for messages
+    pub fn iter(&self) -> CountingIter<'_, T> {
+        CountingIter { receiver: self }
+    }
+}
+
+impl<'a, T> Iterator for CountingIter<'a, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        self.receiver.recv().ok()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a CountingReceiver<T> {
+    type Item = T;
+    type IntoIter = CountingIter<'a, T>;
+    fn into_iter(self) -> CountingIter<'a, T> {
+        self.iter()
+    }
+}
+
+impl Collection for QueueMetrics {
+    fn visit<'x>(&'x self, visitor: &mut dyn Visitor<'x>) {
+        visit_group(visitor, "queue", Some(("queue", &self.name)), &[
+            ("in_flight", &self.in_flight),
+            ("sent", &self.sent),
+            ("received", &self.received),
+            ("dropped", &self.dropped),
+        ]);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::{QueueMetrics, counting_channel, counting_sync_channel};
+
+    #[test]
+    fn dropped_receiver() {
+        let metrics = Arc::new(QueueMetrics::new("test"));
+        let (tx, rx) = counting_channel(metrics.clone());
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+        assert_eq!(rx.recv(), Ok(1));
+        drop(rx);
+        assert_eq!(metrics.in_flight(), 0);
+        assert_eq!(metrics.sent(), 2);
+        assert_eq!(metrics.received(), 1);
+        assert_eq!(metrics.dropped(), 1);
+        assert!(tx.send(3).is_err());
+        assert_eq!(metrics.in_flight(), 0);
+        assert_eq!(metrics.dropped(), 2);
+    }
+
+    #[test]
+    fn full_queue() {
+        let metrics = Arc::new(QueueMetrics::new("test"));
+        let (tx, rx) = counting_sync_channel(1, metrics.clone());
+        tx.try_send(1).unwrap();
+        assert!(tx.try_send(2).is_err());
+        assert_eq!(metrics.in_flight(), 1);
+        assert_eq!(metrics.dropped(), 1);
+        assert_eq!(rx.try_recv(), Ok(1));
+        assert_eq!(metrics.in_flight(), 0);
+    }
+}
@@ -1,16 +1,17 @@
 use std::env;
 use std::fs::{OpenOptions, remove_file, rename};
+use std::fmt::Write as FmtWrite;
 use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 use std::ptr;
 
 use libc;
-use serde_json::{to_string, to_value};
 
 use error::{Error, ErrorEnum};
 use name::Name;
 use value::{Value, RawType};
-use json::JsonName;
+use encode::NameEncoder;
+use filter::{Filter, MapNames};
 use {ActiveCollection};
 
 
@@ -27,6 +28,44 @@ pub trait Visitor<'a> {
 pub trait Collection {
     /// Visit the whole collection, use visitor to report a metric
     fn visit<'x>(&'x self, visitor: &mut Visitor<'x>);
+
+    /// Returns a collection of metrics for which `predicate` returns true
+    ///
+    /// Predicate receives a name and a type of the metric. Use it on
+    /// a reference (`(&coll).filter(..)`) to keep the original collection.
+    fn filter<F>(self, predicate: F) -> Filter<Self, F>
+        where F: Fn(&dyn Name, RawType) -> bool,
+              Self: Sized,
+    {
+        Filter::new(self, predicate)
+    }
+
+    /// Returns a collection where every name is replaced by `f(name)`
+    fn map_names<F, N>(self, f: F) -> MapNames<Self, F>
+        where F: Fn(&dyn Name) -> N,
+              N: Name,
+              Self: Sized,
+    {
+        MapNames::new(self, f)
+    }
+}
+
+/// Report `metrics` named `{"group": group, "metric": .., label}`
+///
+/// A shortcut for built-in collections, `label` is an additional key-value
+/// pair, if any.
+pub fn visit_group<'x>(visitor: &mut dyn Visitor<'x>, group: &str,
+    label: Option<(&str, &str)>, metrics: &[(&str, &'x dyn Value)])
+{
+    for &(metric, value) in metrics {
+        let pairs = [
+            ("group", group),
+            ("metric", metric),
+            label.unwrap_or_default(),
+        ];
+        let len = if label.is_some() { 3 } else { 2 };
+        visitor.metric(&&pairs[..len], value);
+    }
 }
 
 #[cfg(unix)]
@@ -89,39 +128,55 @@ pub fn start<'x, T: Collection + ?Sized>(coll: &'x T)
     use std::os::unix::io::AsRawFd;
     use self::ErrorEnum::*;
 
-    struct Metric<'a> {
-        name: String,
-        raw_type: RawType,
+    struct ListVisitor<'b> {
+        encoder: NameEncoder,
+        metadata: String,
+        pointers: Vec<&'b dyn Value>,
         size: usize,
-        pointer: &'a Value,
+        invalid: Option<(String, String)>,
     }
-    struct ListVisitor<'a, 'b: 'a, 'c: 'a>(&'a mut Vec<Metric<'b>>,
-        &'a mut Vec<&'c Value>, &'a mut usize);
-    impl<'a, 'b: 'a, 'c: 'a> Visitor<'b> for ListVisitor<'a, 'b, 'c> {
-        fn metric(&mut self, name: &Name, value: &'b Value)
+    impl<'b> Visitor<'b> for ListVisitor<'b> {
+        fn metric(&mut self, name: &dyn Name, value: &'b dyn Value)
         {
-            self.0.push(Metric {
-                // must have all keys sorted
-                name: to_string(&to_value(JsonName(name))
-                    .expect("can always serialize"))
-                    .expect("can always serialize"),
-                raw_type: value.raw_type(),
-                size: value.raw_size(),
-                pointer: value,
-            });
-            *self.2 += value.raw_size();
+            let raw_type = value.raw_type();
+            write!(self.metadata, "{main_type} {size}{space}{type_suffix}: ",
+                main_type=raw_type.main_type(),
+                size=value.raw_size(),
+                space=if raw_type.type_suffix().is_some() { " " } else {""},
+                type_suffix=raw_type.type_suffix().unwrap_or(""))
+                .expect("Can always write into buffer");
+            // must have all keys sorted
+            let start = self.metadata.len();
+            self.encoder.encode(name, &mut self.metadata);
+            if let Some(key) = self.encoder.invalid_key() {
+                if self.invalid.is_none() {
+                    self.invalid = Some((self.metadata[start..].to_string(),
+                                         key.to_string()));
+                }
+            }
+            self.metadata.push('\n');
+            self.pointers.push(value);
+            self.size += value.raw_size();
         }
     }
 
-    let mut values_size = 0;
-    let mut pointers = Vec::new();
-    let mut all_metrics = Vec::with_capacity(100);
-    coll.visit(&mut ListVisitor(&mut all_metrics, &mut pointers,
-                                &mut values_size));
+    let mut list = ListVisitor {
+        encoder: NameEncoder::new(),
+        metadata: String::with_capacity(4096),
+        pointers: Vec::with_capacity(100),
+        size: 0,
+        invalid: None,
+    };
+    coll.visit(&mut list);
+    let ListVisitor { metadata: metadata_buf, pointers, size, invalid, .. }
+        = list;
+    if let Some((name, key)) = invalid {
+        return Err(InvalidName(name, key).into());
+    }
 
     // TODO(tailhook) sort metrics by size class
     // TODO(tailhook) find out real page size
-    values_size = (values_size + 4095) & !4095;
+    let values_size = (size + 4095) & !4095;
 
     let (dir, name) = path_from_env(true);
     let tmp_path = dir.join(format!("{}.tmp", name));
@@ -159,23 +214,13 @@ pub fn start<'x, T: Collection + ?Sized>(coll: &'x T)
         metrics: pointers,
         mmap: ptr,
         mmap_size: values_size,
+        retired: false,
     };
 
     let mut offset = 0;
-    let mut metadata_buf = String::with_capacity(4096);
-    let mut pointers = Vec::new();
-    for metric in all_metrics {
-        use std::fmt::Write;
-        write!(metadata_buf, "{main_type} {size}{space}{type_suffix}: {key}\n",
-            main_type=metric.raw_type.main_type(),
-            size=metric.size,
-            space=if metric.raw_type.type_suffix().is_some() { " " } else {""},
-            type_suffix=metric.raw_type.type_suffix().unwrap_or(""),
-            key=metric.name)
-            .expect("Can always write into buffer");
-        metric.pointer.copy_assign(unsafe { ptr.offset(offset as isize) });
-        offset += metric.size;
-        pointers.push(metric.pointer);
+    for metric in &result.metrics {
+        metric.copy_assign(unsafe { ptr.offset(offset as isize) });
+        offset += metric.raw_size();
     }
 
     rename(&tmp_path, &result.values_path)
@@ -202,6 +247,20 @@ pub fn start<'x, T: Collection + ?Sized>(coll: &'x T)
     })
 }
 
+/// Stop using the collection without resetting metrics and removing files
+///
+/// This is used when metrics are already attached to another (newer) file,
+/// so only unmapping memory is left when the collection is dropped.
+#[cfg(all(unix, feature="registry"))]
+pub fn retire(coll: &mut ActiveCollection) {
+    coll.metrics.clear();
+    coll.retired = true;
+}
+
+#[cfg(all(windows, feature="registry"))]
+pub fn retire(_coll: &mut ActiveCollection) {
+}
+
 #[cfg(unix)]
 impl<'a> Drop for ActiveCollection<'a> {
     fn drop(&mut self) {
@@ -213,6 +272,9 @@ impl<'a> Drop for ActiveCollection<'a> {
             let err = io::Error::last_os_error();
             error!("Can't unmap file {:?}: {}", self.values_path, err);
         }
+        if self.retired {
+            return;
+        }
         remove_file(&self.values_path).map_err(|e| {
             error!("Can't unlink path {:?}: {}", self.values_path, e);
         }).ok();
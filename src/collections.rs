@@ -40,3 +40,9 @@ impl<'a, T: Collection+?Sized> Collection for Box<T> {
         (**self).visit(visitor);
     }
 }
+
+impl<T: Collection + ?Sized> Collection for &T {
+    fn visit<'x>(&'x self, visitor: &mut dyn Visitor<'x>) {
+        (**self).visit(visitor);
+    }
+}
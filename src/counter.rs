@@ -71,7 +71,10 @@ impl Describe for Counter {
 
 impl Assign for Counter {
     fn copy_assign(&self, ptr: *mut c_void) {
-        let value = self.value.load(Ordering::SeqCst);
+        // read through the pointer, as we may be attached to another file
+        let value = unsafe {
+            &*self.pointer.load(Ordering::SeqCst)
+        }.load(Ordering::SeqCst);
         let ptr: *mut Atomic<u64> = unsafe { transmute(ptr) };
         unsafe { (*ptr).store(value, Ordering::SeqCst) };
         self.pointer.store(ptr, Ordering::SeqCst);
@@ -0,0 +1,140 @@
+use std::borrow::Borrow;
+use std::io::{self, Read, Write, BufRead, Seek, SeekFrom};
+
+use counter::Counter;
+
+
+/// A reader that counts bytes and read operations
+///
+/// Counters may be anything that borrows a `Counter`: `&'static Counter`
+/// (i.e. a `lazy_static`), `Arc<Counter>`, or an owned `Counter`.
+///
+/// When used as `BufRead`, bytes are counted when consumed and operations
+/// are not counted.
+#[derive(Debug)]
+pub struct CountingReader<R, C> {
+    inner: R,
+    bytes: C,
+    operations: C,
+    errors: Option<C>,
+}
+
+/// A writer that counts bytes and write operations
+///
+/// Counters may be anything that borrows a `Counter`: `&'static Counter`
+/// (i.e. a `lazy_static`), `Arc<Counter>`, or an owned `Counter`.
+#[derive(Debug)]
+pub struct CountingWriter<W, C> {
+    inner: W,
+    bytes: C,
+    operations: C,
+    errors: Option<C>,
+}
+
+fn check<T, C>(errors: &Option<C>, result: io::Result<T>) -> io::Result<T>
+    where C: Borrow<Counter>,
+{
+    if let (Some(errors), Err(e)) = (errors, &result) {
+        match e.kind() {
+            // not really errors, operation should be retried
+            io::ErrorKind::Interrupted | io::ErrorKind::WouldBlock => {}
+            _ => errors.borrow().incr(1),
+        }
+    }
+    result
+}
+
+impl<R, C: Borrow<Counter>> CountingReader<R, C> {
+    /// Wrap a stream, counting bytes and operations
+    pub fn new(inner: R, bytes: C, operations: C) -> CountingReader<R, C> {
+        CountingReader { inner, bytes, operations, errors: None }
+    }
+    /// Also count failed operations using `errors` counter
+    ///
+    /// `Interrupted` and `WouldBlock` errors are not counted.
+    pub fn errors(mut self, errors: C) -> CountingReader<R, C> {
+        self.errors = Some(errors);
+        self
+    }
+    /// Returns a reference to the underlying stream
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+    /// Returns a mutable reference to the underlying stream
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+    /// Unwraps the underlying stream
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Seek, C: Borrow<Counter>> Seek for CountingReader<R, C> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        check(&self.errors, self.inner.seek(pos))
+    }
+}
+
+impl<W, C: Borrow<Counter>> CountingWriter<W, C> {
+    /// Wrap a stream, counting bytes and operations
+    pub fn new(inner: W, bytes: C, operations: C) -> CountingWriter<W, C> {
+        CountingWriter { inner, bytes, operations, errors: None }
+    }
+    /// Also count failed operations using `errors` counter
+    ///
+    /// `Interrupted` and `WouldBlock` errors are not counted.
+    pub fn errors(mut self, errors: C) -> CountingWriter<W, C> {
+        self.errors = Some(errors);
+        self
+    }
+    /// Returns a reference to the underlying stream
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+    /// Returns a mutable reference to the underlying stream
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+    /// Unwraps the underlying stream
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Seek, C: Borrow<Counter>> Seek for CountingWriter<W, C> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        check(&self.errors, self.inner.seek(pos))
+    }
+}
+
+impl<R: Read, C: Borrow<Counter>> Read for CountingReader<R, C> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let bytes = check(&self.errors, self.inner.read(buf))?;
+        self.operations.borrow().incr(1);
+        self.bytes.borrow().incr(bytes as u64);
+        Ok(bytes)
+    }
+}
+
+impl<R: BufRead, C: Borrow<Counter>> BufRead for CountingReader<R, C> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        check(&self.errors, self.inner.fill_buf())
+    }
+    fn consume(&mut self, amt: usize) {
+        self.bytes.borrow().incr(amt as u64);
+        self.inner.consume(amt)
+    }
+}
+
+impl<W: Write, C: Borrow<Counter>> Write for CountingWriter<W, C> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let bytes = check(&self.errors, self.inner.write(buf))?;
+        self.operations.borrow().incr(1);
+        self.bytes.borrow().incr(bytes as u64);
+        Ok(bytes)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        check(&self.errors, self.inner.flush())
+    }
+}
@@ -0,0 +1,157 @@
+use std::fmt::Write;
+
+use name::{Name, NameVisitor};
+
+
+/// Encodes names into canonical form used in cantal metadata
+///
+/// The canonical form is a JSON object with keys sorted, i.e.
+/// `{"group":"http","metric":"requests"}`. It is the same as serializing
+/// the name with `serde_json`, but the encoder keeps its buffers between
+/// calls, so encoding a lot of names doesn't allocate.
+#[derive(Debug, Default)]
+pub struct NameEncoder {
+    data: String,
+    // (key start, key end = value start, value end) offsets into `data`
+    pairs: Vec<(usize, usize, usize)>,
+    invalid: Option<String>,
+}
+
+struct PairsVisitor<'a>(&'a mut NameEncoder);
+
+impl<'a> NameVisitor for PairsVisitor<'a> {
+    fn visit_pair(&mut self, key: &str, value: &str) {
+        let data = &mut self.0.data;
+        let start = data.len();
+        data.push_str(key);
+        let middle = data.len();
+        data.push_str(value);
+        self.0.pairs.push((start, middle, data.len()));
+    }
+    fn visit_invalid(&mut self, key: &str) {
+        if self.0.invalid.is_none() {
+            self.0.invalid = Some(key.to_string());
+        }
+    }
+}
+
+fn escape(value: &str, buf: &mut String) {
+    buf.push('"');
+    let mut start = 0;
+    for (idx, byte) in value.bytes().enumerate() {
+        let replacement = match byte {
+            b'"' => "\\\"",
+            b'\\' => "\\\\",
+            b'\n' => "\\n",
+            b'\r' => "\\r",
+            b'\t' => "\\t",
+            0x08 => "\\b",
+            0x0c => "\\f",
+            0x00..=0x1f => "",
+            _ => continue,
+        };
+        buf.push_str(&value[start..idx]);
+        if replacement.is_empty() {
+            write!(buf, "\\u{:04x}", byte).expect("can always write");
+        } else {
+            buf.push_str(replacement);
+        }
+        start = idx + 1;
+    }
+    buf.push_str(&value[start..]);
+    buf.push('"');
+}
+
+impl NameEncoder {
+    /// Create a new encoder
+    pub fn new() -> NameEncoder {
+        NameEncoder::default()
+    }
+    /// Append canonical representation of the name to the buffer
+    ///
+    /// If the name has duplicate keys, the last value is used. Keys
+    /// reported as invalid are skipped, see `invalid_key`.
+    pub fn encode(&mut self, name: &dyn Name, buf: &mut String) {
+        self.data.clear();
+        self.pairs.clear();
+        self.invalid = None;
+        name.visit(&mut PairsVisitor(self));
+        let data = &self.data;
+        // stable sort, so the last of the equal keys is the last one visited
+        self.pairs.sort_by(|a, b| data[a.0..a.1].cmp(&data[b.0..b.1]));
+        buf.push('{');
+        let mut first = true;
+        for (idx, &(kstart, vstart, vend)) in self.pairs.iter().enumerate() {
+            let key = &data[kstart..vstart];
+            if let Some(&(nstart, nend, _)) = self.pairs.get(idx+1) {
+                if &data[nstart..nend] == key {
+                    continue;
+                }
+            }
+            if !first {
+                buf.push(',');
+            }
+            first = false;
+            escape(key, buf);
+            buf.push(':');
+            escape(&data[vstart..vend], buf);
+        }
+        buf.push('}');
+    }
+    /// Returns first invalid key of the last encoded name
+    ///
+    /// See `Strict` for when keys are considered invalid.
+    pub fn invalid_key(&self) -> Option<&str> {
+        self.invalid.as_ref().map(|x| &x[..])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use serde_json;
+    use super::NameEncoder;
+
+    fn encode(pairs: &[(&str, &str)]) -> String {
+        let mut buf = String::new();
+        NameEncoder::new().encode(&pairs, &mut buf);
+        buf
+    }
+
+    fn expected(pairs: &[(&str, &str)]) -> String {
+        let map = pairs.iter().cloned().collect::<BTreeMap<_, _>>();
+        serde_json::to_string(&map).unwrap()
+    }
+
+    #[test]
+    fn unsorted() {
+        let pairs = &[("metric", "requests"), ("group", "http"), ("a", "")];
+        assert_eq!(encode(pairs),
+                   r#"{"a":"","group":"http","metric":"requests"}"#);
+        assert_eq!(encode(pairs), expected(pairs));
+    }
+
+    #[test]
+    fn duplicate_keys() {
+        let pairs = &[("metric", "a"), ("group", "x"), ("metric", "b"),
+                      ("group", "y"), ("metric", "c")];
+        assert_eq!(encode(pairs), r#"{"group":"y","metric":"c"}"#);
+        assert_eq!(encode(pairs), expected(pairs));
+    }
+
+    #[test]
+    fn escaping() {
+        let pairs = &[
+            ("metric", "quote \" backslash \\ slash /"),
+            ("control", "\n\r\t\x08\x0c\x00\x1f\x7f"),
+            ("key \"\\\u{1}", "unicode \u{444}\u{1F600}"),
+        ];
+        assert_eq!(encode(pairs), expected(pairs));
+    }
+
+    #[test]
+    fn empty() {
+        assert_eq!(encode(&[]), expected(&[]));
+    }
+}
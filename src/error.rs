@@ -43,5 +43,13 @@ quick_error! {
             display("error parsing metadata file {:?}: {}", path, err)
             description(err)
         }
+        InvalidName(name: String, key: String) {
+            display("metric {} has non-string value for key {:?}", name, key)
+            description("metric name has non-string value")
+        }
+        RegistryStarted {
+            display("registry is already started")
+            description("registry is already started")
+        }
     }
 }
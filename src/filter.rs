@@ -0,0 +1,85 @@
+use collection::{Collection, Visitor};
+use name::Name;
+use value::{Value, RawType};
+
+
+/// A collection returned by `Collection::filter`
+pub struct Filter<C, F> {
+    inner: C,
+    predicate: F,
+}
+
+/// A collection returned by `Collection::map_names`
+pub struct MapNames<C, F> {
+    inner: C,
+    f: F,
+}
+
+struct FilterVisitor<'a, 'x: 'a, F: 'a> {
+    inner: &'a mut dyn Visitor<'x>,
+    predicate: &'a F,
+}
+
+struct MapVisitor<'a, 'x: 'a, F: 'a> {
+    inner: &'a mut dyn Visitor<'x>,
+    f: &'a F,
+}
+
+impl<C, F> Filter<C, F> {
+    /// Create a filtered collection, same as `Collection::filter`
+    pub fn new(inner: C, predicate: F) -> Filter<C, F> {
+        Filter { inner, predicate }
+    }
+}
+
+impl<C, F> MapNames<C, F> {
+    /// Create a collection with mapped names, same as
+    /// `Collection::map_names`
+    pub fn new(inner: C, f: F) -> MapNames<C, F> {
+        MapNames { inner, f }
+    }
+}
+
+impl<'a, 'x: 'a, F> Visitor<'x> for FilterVisitor<'a, 'x, F>
+    where F: Fn(&dyn Name, RawType) -> bool
+{
+    fn metric(&mut self, name: &dyn Name, value: &'x dyn Value) {
+        if (self.predicate)(name, value.raw_type()) {
+            self.inner.metric(name, value);
+        }
+    }
+}
+
+impl<'a, 'x: 'a, F, N> Visitor<'x> for MapVisitor<'a, 'x, F>
+    where F: Fn(&dyn Name) -> N,
+          N: Name,
+{
+    fn metric(&mut self, name: &dyn Name, value: &'x dyn Value) {
+        self.inner.metric(&(self.f)(name), value);
+    }
+}
+
+impl<C, F> Collection for Filter<C, F>
+    where C: Collection,
+          F: Fn(&dyn Name, RawType) -> bool,
+{
+    fn visit<'x>(&'x self, visitor: &mut dyn Visitor<'x>) {
+        self.inner.visit(&mut FilterVisitor {
+            inner: visitor,
+            predicate: &self.predicate,
+        });
+    }
+}
+
+impl<C, F, N> Collection for MapNames<C, F>
+    where C: Collection,
+          F: Fn(&dyn Name) -> N,
+          N: Name,
+{
+    fn visit<'x>(&'x self, visitor: &mut dyn Visitor<'x>) {
+        self.inner.visit(&mut MapVisitor {
+            inner: visitor,
+            f: &self.f,
+        });
+    }
+}
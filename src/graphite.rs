@@ -0,0 +1,63 @@
+use std::io::{self, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use collection::{Collection, Visitor};
+use name::Name;
+use path::dotted_path;
+use value::{Value, RawType};
+
+
+struct GraphiteVisitor<'a, W: Write + 'a> {
+    out: &'a mut W,
+    order: &'a [&'a str],
+    timestamp: u64,
+    path: String,
+    error: Option<io::Error>,
+}
+
+impl<'a, 'b, W: Write + 'a> Visitor<'b> for GraphiteVisitor<'a, W> {
+    fn metric(&mut self, name: &dyn Name, value: &dyn Value) {
+        if self.error.is_some() {
+            return;
+        }
+        match value.raw_type() {
+            RawType::Counter | RawType::Level(_) => {}
+            RawType::State => return,
+        }
+        self.path.clear();
+        dotted_path(name, self.order, &mut self.path);
+        if let Err(e) = writeln!(self.out, "{} {} {}",
+            self.path, value.as_json(), self.timestamp)
+        {
+            self.error = Some(e);
+        }
+    }
+}
+
+/// Write all metrics of a collection in graphite plaintext format
+///
+/// Every metric is written as `path value timestamp` line. The path is
+/// built from values of the name: keys listed in `order` go first (in that
+/// order) then all other keys sorted. For example, with order
+/// `["group", "metric"]` name `{"group": "http", "metric": "requests"}`
+/// becomes `http.requests`. State values are skipped.
+pub fn graphite<C, W>(col: &C, order: &[&str], time: SystemTime, mut out: W)
+    -> io::Result<()>
+    where C: Collection + ?Sized,
+          W: Write,
+{
+    let timestamp = time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs()).unwrap_or(0);
+    let mut visitor = GraphiteVisitor {
+        out: &mut out,
+        order,
+        timestamp,
+        path: String::with_capacity(64),
+        error: None,
+    };
+    col.visit(&mut visitor);
+    match visitor.error {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
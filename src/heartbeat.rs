@@ -0,0 +1,168 @@
+use std::io;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use collection::{Collection, Visitor, visit_group};
+use integer::Integer;
+use periodic::{Periodic, spawn};
+use time::{millis, now_ms};
+
+
+/// Process start time, uptime and a heartbeat
+///
+/// Named `{"group": "process", "metric": ..}`:
+///
+/// * `start_time_ms` -- when the process was started, in milliseconds since
+///   the epoch (on platforms other than linux, when metrics were created,
+///   so create them early in `main`)
+/// * `uptime_ms` -- milliseconds since start time
+/// * `heartbeat_ms` -- time when the application was last seen alive, in
+///   milliseconds since the epoch
+///
+/// Call `beat` to update both uptime and heartbeat right away. Or start
+/// `heartbeat` thread and call `touch` from the main loop of the
+/// application: the thread updates uptime every interval, but heartbeat
+/// only if `touch` was called since the previous update. So if the
+/// application deadlocks, the heartbeat goes stale, while uptime shows that
+/// the process itself is still there.
+pub struct HeartbeatMetrics {
+    started: Instant,
+    alive: AtomicBool,
+    start_time_ms: Integer,
+    uptime_ms: Integer,
+    heartbeat_ms: Integer,
+}
+
+/// A guard of the thread started by `heartbeat`
+///
+/// When dropped, the thread is stopped.
+pub struct HeartbeatGuard {
+    _periodic: Periodic,
+}
+
+// Process start time from `/proc`, in milliseconds since the epoch
+#[cfg(target_os="linux")]
+fn process_start_ms() -> Option<i64> {
+    use std::fs::read_to_string;
+    use libc;
+
+    let stat = read_to_string("/proc/self/stat").ok()?;
+    // command name may contain spaces and parenthesis, so fields are
+    // counted from the last paren, `starttime` is the 22nd field
+    let ticks: u64 = stat[stat.rfind(')')?+1..].split_whitespace()
+        .nth(19)?.parse().ok()?;
+    let boot: u64 = read_to_string("/proc/stat").ok()?.lines()
+        .find(|line| line.starts_with("btime "))?[6..].trim().parse().ok()?;
+    let hz = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    if hz <= 0 {
+        return None;
+    }
+    Some((boot * 1000 + ticks * 1000 / hz as u64) as i64)
+}
+
+#[cfg(not(target_os="linux"))]
+fn process_start_ms() -> Option<i64> {
+    None
+}
+
+impl HeartbeatMetrics {
+    /// Create metrics with start time of the process
+    ///
+    /// Start time is set to now if it can't be read from the system.
+    pub fn new() -> HeartbeatMetrics {
+        let now = now_ms() as i64;
+        let start_time = process_start_ms()
+            .filter(|&start| start <= now)
+            .unwrap_or(now);
+        let started = Instant::now();
+        let metrics = HeartbeatMetrics {
+            started: started
+                .checked_sub(Duration::from_millis((now - start_time) as u64))
+                .unwrap_or(started),
+            alive: AtomicBool::new(false),
+            start_time_ms: Integer::new(),
+            uptime_ms: Integer::new(),
+            heartbeat_ms: Integer::new(),
+        };
+        metrics.start_time_ms.set(start_time);
+        metrics.heartbeat_ms.set(now);
+        metrics
+    }
+    /// Update uptime and heartbeat time
+    pub fn beat(&self) {
+        self.uptime_ms.set(millis(self.started.elapsed()) as i64);
+        self.heartbeat_ms.set(now_ms() as i64);
+    }
+    /// Mark application as alive, heartbeat is updated by `heartbeat` thread
+    ///
+    /// This is just an atomic store, so it's fine to call it on every
+    /// iteration of the main loop.
+    pub fn touch(&self) {
+        self.alive.store(true, Ordering::Relaxed);
+    }
+    fn tick(&self) {
+        self.uptime_ms.set(millis(self.started.elapsed()) as i64);
+        if self.alive.swap(false, Ordering::Relaxed) {
+            self.heartbeat_ms.set(now_ms() as i64);
+        }
+    }
+}
+
+impl Default for HeartbeatMetrics {
+    fn default() -> HeartbeatMetrics {
+        HeartbeatMetrics::new()
+    }
+}
+
+impl Collection for HeartbeatMetrics {
+    fn visit<'x>(&'x self, visitor: &mut dyn Visitor<'x>) {
+        visit_group(visitor, "process", None, &[
+            ("start_time_ms", &self.start_time_ms),
+            ("uptime_ms", &self.uptime_ms),
+            ("heartbeat_ms", &self.heartbeat_ms),
+        ]);
+    }
+}
+
+/// Update uptime and heartbeat every `interval` in a background thread
+///
+/// Heartbeat is only updated if `touch` was called since the previous
+/// update, so call it at least once per `interval` from the main loop.
+/// The thread does nothing else, so it's cheap to run it every second.
+pub fn heartbeat(metrics: Arc<HeartbeatMetrics>, interval: Duration)
+    -> io::Result<HeartbeatGuard>
+{
+    metrics.beat();
+    let periodic = spawn("cantal-heartbeat", interval, false, move || {
+        metrics.tick();
+    })?;
+    Ok(HeartbeatGuard {
+        _periodic: periodic,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    use super::{HeartbeatMetrics, heartbeat};
+
+    #[test]
+    fn stale_unless_touched() {
+        let metrics = Arc::new(HeartbeatMetrics::new());
+        let _guard = heartbeat(metrics.clone(), Duration::from_millis(10))
+            .expect("can start thread");
+        let first = metrics.heartbeat_ms.get();
+        let uptime = metrics.uptime_ms.get();
+        sleep(Duration::from_millis(100));
+        // application is "stuck", only uptime is updated
+        assert_eq!(metrics.heartbeat_ms.get(), first);
+        assert!(metrics.uptime_ms.get() > uptime);
+        metrics.touch();
+        sleep(Duration::from_millis(100));
+        assert!(metrics.heartbeat_ms.get() > first);
+    }
+}
@@ -0,0 +1,276 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::ser::{Serialize, Serializer, SerializeMap, SerializeSeq};
+use serde_json::Value as Json;
+
+use collection::{Collection, Visitor};
+use encode::NameEncoder;
+use name::Name;
+use periodic::{Periodic, spawn};
+use snapshot::{MetricValue, PairsVisitor};
+use time::{millis, now_ms};
+use value::{Value, RawType, LevelKind};
+
+
+/// Last values of every metric of a collection
+///
+/// Keeps up to `capacity` samples per metric, older samples are discarded.
+/// Samples are added by `record_history` (or manually by `snapshot`), and
+/// may be queried at any time, or serialized for a debugging endpoint:
+///
+/// ```rust
+/// # extern crate libcantal;
+/// # extern crate serde_json;
+/// # use std::time::Duration;
+/// # use libcantal::{Counter, History, Value};
+/// # fn main() {
+/// let requests = Counter::new();
+/// let metrics: &[(&[(&str, &str)], &dyn Value)] = &[
+///     (&[("metric", "requests")], &requests),
+/// ];
+/// let history = History::new(60);
+/// requests.incr(10);
+/// history.snapshot(metrics);
+/// requests.incr(5);
+/// history.snapshot(metrics);
+///
+/// let name: &[(&str, &str)] = &[("metric", "requests")];
+/// assert_eq!(history.last(&name, 1)[0].value.as_f64(), Some(15.));
+/// assert_eq!(history.min(&name, Duration::from_secs(60)), Some(10.));
+/// println!("{}", serde_json::to_string(&history).unwrap());
+/// # }
+/// ```
+///
+/// Serialized as a list of pairs: name and list of samples, the latter are
+/// objects with `timestamp` and `value` keys, where value is serialized like
+/// in `Json`.
+pub struct History {
+    capacity: usize,
+    data: Mutex<Data>,
+}
+
+/// A single value of a metric in `History`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    /// Time of the snapshot in milliseconds since the epoch
+    pub timestamp: u64,
+    /// The value of the metric
+    pub value: MetricValue,
+}
+
+/// A guard of the thread started by `record_history`
+///
+/// When dropped, the last snapshot is taken and the thread is stopped.
+pub struct HistoryRecorderGuard {
+    _periodic: Periodic,
+}
+
+struct Series {
+    name: BTreeMap<String, String>,
+    samples: VecDeque<Sample>,
+    // number of the snapshot that has seen the metric last time
+    seen: u64,
+}
+
+struct Data {
+    encoder: NameEncoder,
+    buf: String,
+    series: HashMap<String, Series>,
+    generation: u64,
+}
+
+struct SnapshotVisitor<'a> {
+    capacity: usize,
+    timestamp: u64,
+    data: &'a mut Data,
+}
+
+fn metric_value(value: &dyn Value) -> Option<MetricValue> {
+    match (value.raw_type(), value.as_json()) {
+        (RawType::Counter, Json::Number(ref n)) => {
+            n.as_u64().map(MetricValue::Counter)
+        }
+        (RawType::Level(LevelKind::Signed), Json::Number(ref n)) => {
+            n.as_i64().map(MetricValue::Integer)
+        }
+        (RawType::Level(LevelKind::Float), Json::Number(ref n)) => {
+            n.as_f64().map(MetricValue::Float)
+        }
+        (RawType::State, Json::String(s)) => Some(MetricValue::State(s)),
+        _ => None,
+    }
+}
+
+impl<'a, 'b> Visitor<'b> for SnapshotVisitor<'a> {
+    fn metric(&mut self, name: &dyn Name, value: &dyn Value) {
+        let value = match metric_value(value) {
+            Some(value) => value,
+            None => return,
+        };
+        let data = &mut *self.data;
+        data.buf.clear();
+        data.encoder.encode(name, &mut data.buf);
+        if !data.series.contains_key(&data.buf) {
+            let mut pairs = BTreeMap::new();
+            name.visit(&mut PairsVisitor(&mut pairs));
+            data.series.insert(data.buf.clone(), Series {
+                name: pairs,
+                samples: VecDeque::with_capacity(self.capacity),
+                seen: 0,
+            });
+        }
+        let series = data.series.get_mut(&data.buf).expect("just inserted");
+        if series.samples.len() >= self.capacity {
+            series.samples.pop_front();
+        }
+        series.samples.push_back(Sample {
+            timestamp: self.timestamp,
+            value,
+        });
+        series.seen = data.generation;
+    }
+}
+
+impl Series {
+    fn window(&self, window: Duration) -> impl Iterator<Item=&Sample> {
+        let since = now_ms().saturating_sub(millis(window));
+        self.samples.iter().filter(move |s| s.timestamp >= since)
+    }
+}
+
+impl History {
+    /// Create a history keeping `capacity` samples of each metric
+    pub fn new(capacity: usize) -> History {
+        History {
+            capacity: capacity.max(1),
+            data: Mutex::new(Data {
+                encoder: NameEncoder::new(),
+                buf: String::with_capacity(256),
+                series: HashMap::new(),
+                generation: 0,
+            }),
+        }
+    }
+    /// Add current values of the collection as a new sample
+    ///
+    /// Metrics that are not in the collection for `capacity` snapshots
+    /// in a row are forgotten.
+    pub fn snapshot<C: Collection + ?Sized>(&self, coll: &C) {
+        let mut data = self.data.lock().expect("history is not poisoned");
+        data.generation += 1;
+        coll.visit(&mut SnapshotVisitor {
+            capacity: self.capacity,
+            timestamp: now_ms(),
+            data: &mut data,
+        });
+        let oldest = data.generation.saturating_sub(self.capacity as u64);
+        data.series.retain(|_, s| s.seen > oldest);
+    }
+    fn with_series<F, R>(&self, name: &dyn Name, f: F) -> Option<R>
+        where F: FnOnce(&Series) -> Option<R>
+    {
+        let mut data = self.data.lock().expect("history is not poisoned");
+        let data = &mut *data;
+        data.buf.clear();
+        data.encoder.encode(name, &mut data.buf);
+        data.series.get(&data.buf).and_then(f)
+    }
+    /// Returns up to `n` last samples of the metric, oldest first
+    pub fn last(&self, name: &dyn Name, n: usize) -> Vec<Sample> {
+        self.with_series(name, |s| {
+            let skip = s.samples.len().saturating_sub(n);
+            Some(s.samples.iter().skip(skip).cloned().collect())
+        }).unwrap_or_default()
+    }
+    /// Returns change of the metric per second over last `window`
+    ///
+    /// Counter resets (i.e. by `start_with_reading`) are taken into account.
+    /// Returns `None` if there are less than two numeric samples in the
+    /// window.
+    pub fn rate(&self, name: &dyn Name, window: Duration) -> Option<f64> {
+        self.with_series(name, |s| {
+            let mut first = None;
+            let mut last = None;
+            let mut delta = 0.;
+            for sample in s.window(window) {
+                let value = match sample.value.as_f64() {
+                    Some(value) => value,
+                    None => continue,
+                };
+                if let Some((_, prev)) = last {
+                    delta += match sample.value {
+                        MetricValue::Counter(_) if value < prev => value,
+                        _ => value - prev,
+                    };
+                }
+                first = first.or(Some(sample.timestamp));
+                last = Some((sample.timestamp, value));
+            }
+            match (first, last) {
+                (Some(first), Some((last, _))) if last > first => {
+                    Some(delta * 1000. / (last - first) as f64)
+                }
+                _ => None,
+            }
+        })
+    }
+    /// Returns minimum value of the metric over last `window`
+    pub fn min(&self, name: &dyn Name, window: Duration) -> Option<f64> {
+        self.with_series(name, |s| {
+            s.window(window).filter_map(|x| x.value.as_f64())
+                .fold(None, |min, x| Some(min.map_or(x, |m: f64| m.min(x))))
+        })
+    }
+    /// Returns maximum value of the metric over last `window`
+    pub fn max(&self, name: &dyn Name, window: Duration) -> Option<f64> {
+        self.with_series(name, |s| {
+            s.window(window).filter_map(|x| x.value.as_f64())
+                .fold(None, |max, x| Some(max.map_or(x, |m: f64| m.max(x))))
+        })
+    }
+}
+
+impl Serialize for Sample {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        let mut map = serializer.serialize_map(Some(2))?;
+        map.serialize_entry("timestamp", &self.timestamp)?;
+        map.serialize_entry("value", &self.value)?;
+        map.end()
+    }
+}
+
+impl Serialize for History {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        let data = self.data.lock().expect("history is not poisoned");
+        let mut series = data.series.values().collect::<Vec<_>>();
+        series.sort_by(|a, b| a.name.cmp(&b.name));
+        let mut seq = serializer.serialize_seq(Some(series.len()))?;
+        for s in series {
+            seq.serialize_element(&(&s.name, &s.samples))?;
+        }
+        seq.end()
+    }
+}
+
+/// Take a snapshot of the collection into `history` every `interval`
+///
+/// So history covers `capacity * interval` of time.
+pub fn record_history<C>(history: Arc<History>, interval: Duration,
+    coll: Arc<C>)
+    -> io::Result<HistoryRecorderGuard>
+    where C: Collection + Send + Sync + ?Sized + 'static,
+{
+    let periodic = spawn("cantal-history", interval, true, move || {
+        history.snapshot(&*coll);
+    })?;
+    Ok(HistoryRecorderGuard {
+        _periodic: periodic,
+    })
+}
@@ -0,0 +1,319 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream, SocketAddr, ToSocketAddrs};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use serde_json::to_vec;
+
+use collection::Collection;
+use json::Json;
+use prometheus::prometheus;
+
+
+const TIMEOUT: u64 = 5;
+const MAX_HEADERS: usize = 100;
+const MAX_LINE: u64 = 8192;
+
+/// A guard of the http server started by `serve_http`
+///
+/// When it's dropped the server is stopped and its thread is joined.
+pub struct HttpServer {
+    addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+enum Format {
+    Json,
+    Prometheus,
+}
+
+/// Serve metrics of a collection over HTTP in a background thread
+///
+/// The following paths are served:
+///
+/// * `/` and `/json` -- the collection serialized with `Json`
+/// * `/metrics` -- the collection in prometheus text format
+///
+/// Requests are handled one by one, so this is only suitable for
+/// occasional scraping and debugging, not for heavy load. Request line and
+/// headers longer than 8 KiB are rejected with `400 Bad Request`, and
+/// connection is closed if request is not handled in 5 seconds.
+pub fn serve_http<A, C>(addr: A, coll: Arc<C>) -> io::Result<HttpServer>
+    where A: ToSocketAddrs,
+          C: Collection + Send + Sync + ?Sized + 'static,
+{
+    let listener = TcpListener::bind(addr)?;
+    let addr = listener.local_addr()?;
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread = thread::Builder::new()
+        .name("cantal-http".into())
+        .spawn({
+            let stop = stop.clone();
+            move || {
+                for stream in listener.incoming() {
+                    if stop.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    match stream {
+                        Ok(stream) => {
+                            let timeout = Duration::new(TIMEOUT, 0);
+                            handle(stream, &*coll, timeout).map_err(|e| {
+                                debug!("Error serving metrics: {}", e);
+                            }).ok();
+                        }
+                        Err(e) => {
+                            warn!("Error accepting connection: {}", e);
+                        }
+                    }
+                }
+            }
+        })?;
+    Ok(HttpServer {
+        addr,
+        stop,
+        thread: Some(thread),
+    })
+}
+
+impl HttpServer {
+    /// Returns the address the server is listening on
+    ///
+    /// Useful when it's started on port zero.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+// A stream that fails when the deadline passes, the socket timeout only
+// limits a single read or write, so a slow client could hold the
+// (only) thread of the server for much longer
+struct Deadline<'a> {
+    stream: &'a TcpStream,
+    deadline: Instant,
+}
+
+impl<'a> Deadline<'a> {
+    fn remaining(&self) -> io::Result<Duration> {
+        let now = Instant::now();
+        if now >= self.deadline {
+            return Err(io::Error::new(io::ErrorKind::TimedOut,
+                                      "request deadline exceeded"));
+        }
+        Ok(self.deadline - now)
+    }
+}
+
+impl<'a> Read for Deadline<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.stream.set_read_timeout(Some(self.remaining()?))?;
+        self.stream.read(buf)
+    }
+}
+
+impl<'a> Write for Deadline<'a> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stream.set_write_timeout(Some(self.remaining()?))?;
+        self.stream.write(buf)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+/// Reads a line of at most `MAX_LINE` bytes, returns false if it's longer
+fn read_line<R: BufRead>(reader: &mut R, line: &mut String)
+    -> io::Result<bool>
+{
+    line.clear();
+    let bytes = reader.by_ref().take(MAX_LINE).read_line(line)?;
+    Ok(bytes as u64 != MAX_LINE || line.ends_with('\n'))
+}
+
+// The whole request, including sending response, must fit in `timeout`
+fn handle<C: Collection + ?Sized>(stream: TcpStream, coll: &C,
+    timeout: Duration)
+    -> io::Result<()>
+{
+    let deadline = Instant::now() + timeout;
+    let mut reader = BufReader::new(Deadline { stream: &stream, deadline });
+    let mut out = Deadline { stream: &stream, deadline };
+    let mut line = String::with_capacity(256);
+    if !read_line(&mut reader, &mut line)? {
+        return respond(&mut out, "400 Bad Request", "text/plain",
+                       b"Request Line Too Long\n", true);
+    }
+    let (method, path) = {
+        let mut words = line.split_whitespace();
+        let method = words.next().unwrap_or("").to_string();
+        let path = words.next().unwrap_or("");
+        let path = path.split('?').next().unwrap_or("").to_string();
+        (method, path)
+    };
+    // skip headers, we don't need any of them
+    for _ in 0..MAX_HEADERS {
+        if !read_line(&mut reader, &mut line)? {
+            return respond(&mut out, "400 Bad Request", "text/plain",
+                           b"Header Too Long\n", true);
+        }
+        if line.trim().is_empty() {
+            break;
+        }
+    }
+    let format = match &path[..] {
+        "/" | "/json" => Format::Json,
+        "/metrics" => Format::Prometheus,
+        _ => return respond(&mut out, "404 Not Found", "text/plain",
+                            b"Not Found\n", true),
+    };
+    let head_only = match &method[..] {
+        "GET" => false,
+        "HEAD" => true,
+        _ => return respond(&mut out, "405 Method Not Allowed", "text/plain",
+                            b"Method Not Allowed\n", true),
+    };
+    match format {
+        Format::Json => {
+            let body = to_vec(&Json(coll)).map_err(io::Error::other)?;
+            respond(&mut out, "200 OK", "application/json",
+                    &body, !head_only)
+        }
+        Format::Prometheus => {
+            let mut body = Vec::new();
+            prometheus(coll, &mut body)?;
+            respond(&mut out, "200 OK", "text/plain; version=0.0.4",
+                    &body, !head_only)
+        }
+    }
+}
+
+fn respond<W: Write>(out: &mut W, status: &str, content_type: &str,
+    body: &[u8], send_body: bool)
+    -> io::Result<()>
+{
+    write!(out, "HTTP/1.0 {}\r\n\
+                 Content-Type: {}\r\n\
+                 Content-Length: {}\r\n\
+                 Connection: close\r\n\
+                 \r\n",
+           status, content_type, body.len())?;
+    if send_body {
+        out.write_all(body)?;
+    }
+    out.flush()
+}
+
+impl Drop for HttpServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        // wake up the thread blocked in accept
+        let mut addr = self.addr;
+        match addr.ip() {
+            IpAddr::V4(ip) if ip.is_unspecified() => {
+                addr.set_ip(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)));
+            }
+            IpAddr::V6(ip) if ip.is_unspecified() => {
+                addr.set_ip(IpAddr::V6(Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 1)));
+            }
+            _ => {}
+        }
+        match TcpStream::connect(addr) {
+            Ok(_) => {
+                if let Some(thread) = self.thread.take() {
+                    thread.join().map_err(|_| {
+                        error!("Metrics http thread panicked");
+                    }).ok();
+                }
+            }
+            Err(e) => {
+                // Detach the thread, it will exit on the next connection
+                error!("Can't stop metrics http server at {}: {}", addr, e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{Read, Write};
+    use std::net::{Shutdown, TcpListener, TcpStream};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use counter::Counter;
+    use value::Value;
+    use super::{serve_http, handle};
+
+    fn request(data: &[u8]) -> String {
+        let counter: &'static Counter = Box::leak(Box::new(Counter::new()));
+        counter.incr(7);
+        let metrics = Arc::new(vec![
+            (json!({"metric": "requests"}), counter as &dyn Value),
+        ]);
+        let server = serve_http("127.0.0.1:0", metrics.clone())
+            .expect("can bind");
+        let mut conn = TcpStream::connect(server.local_addr())
+            .expect("can connect");
+        // server doesn't read too long requests to the end, so it may
+        // close connection before we've sent everything
+        conn.write_all(data).and_then(|()| conn.shutdown(Shutdown::Write))
+            .ok();
+        let mut response = Vec::new();
+        conn.read_to_end(&mut response).ok();
+        String::from_utf8(response).expect("valid utf-8")
+    }
+
+    #[test]
+    fn metrics() {
+        let response = request(b"GET /metrics HTTP/1.0\r\n\
+                                 Host: localhost\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.0 200 OK\r\n"), "{}", response);
+        assert!(response.ends_with("requests 7\n"), "{}", response);
+    }
+
+    #[test]
+    fn long_request_line() {
+        let mut data = b"GET /".to_vec();
+        data.extend(vec![b'a'; 100000]);
+        let response = request(&data);
+        assert!(response.starts_with("HTTP/1.0 400 Bad Request\r\n"),
+                "{}", response);
+    }
+
+    #[test]
+    fn long_header() {
+        let mut data = b"GET / HTTP/1.0\r\nX-Header: ".to_vec();
+        data.extend(vec![b'a'; 100000]);
+        let response = request(&data);
+        assert!(response.starts_with("HTTP/1.0 400 Bad Request\r\n"),
+                "{}", response);
+    }
+
+    #[test]
+    fn slow_client() {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("can bind");
+        let addr = listener.local_addr().expect("bound");
+        let client = thread::spawn(move || {
+            let mut conn = TcpStream::connect(addr).expect("can connect");
+            // every read succeeds, but request never ends
+            for _ in 0..40 {
+                if conn.write_all(b"a").is_err() {
+                    break;
+                }
+                thread::sleep(Duration::from_millis(50));
+            }
+        });
+        let (stream, _) = listener.accept().expect("can accept");
+        let start = Instant::now();
+        let metrics: Vec<(::serde_json::Value, &dyn Value)> = Vec::new();
+        let result = handle(stream, &metrics,
+                            Duration::from_millis(300));
+        assert!(result.is_err());
+        assert!(start.elapsed() < Duration::from_secs(1));
+        client.join().expect("client works");
+    }
+}
@@ -0,0 +1,139 @@
+use std::io::{self, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json::Value as Json;
+
+use collection::{Collection, Visitor};
+use name::{Name, NameVisitor};
+use value::{Value, RawType, LevelKind};
+
+
+struct InfluxVisitor<'a, W: Write + 'a> {
+    out: &'a mut W,
+    measurement_key: &'a str,
+    timestamp: u64,
+    error: Option<io::Error>,
+}
+
+#[derive(Default)]
+struct LineVisitor<'a> {
+    measurement_key: &'a str,
+    measurement: Option<String>,
+    field: Option<String>,
+    tags: Vec<(String, String)>,
+}
+
+impl<'a> NameVisitor for LineVisitor<'a> {
+    fn visit_pair(&mut self, key: &str, value: &str) {
+        if key == self.measurement_key {
+            self.measurement = Some(value.to_string());
+        } else if key == "metric" {
+            self.field = Some(value.to_string());
+        } else {
+            self.tags.push((key.to_string(), value.to_string()));
+        }
+    }
+}
+
+fn escape(value: &str, special: &[char], buf: &mut String) {
+    for c in value.chars() {
+        if special.contains(&c) {
+            buf.push('\\');
+        }
+        buf.push(c);
+    }
+}
+
+fn format_value(value: &dyn Value, buf: &mut String) -> bool {
+    use std::fmt::Write;
+
+    match (value.raw_type(), value.as_json()) {
+        (RawType::Counter, Json::Number(ref n)) => {
+            if n.is_i64() {
+                write!(buf, "{}i", n)
+            } else {
+                write!(buf, "{}u", n)
+            }
+        }
+        (RawType::Level(LevelKind::Signed), Json::Number(ref n)) => {
+            write!(buf, "{}i", n)
+        }
+        (RawType::Level(LevelKind::Float), Json::Number(ref n)) => {
+            write!(buf, "{}", n)
+        }
+        (RawType::State, Json::String(ref s)) => {
+            buf.push('"');
+            escape(s, &['"', '\\'], buf);
+            buf.push('"');
+            Ok(())
+        }
+        _ => return false,
+    }.expect("can always write");
+    true
+}
+
+impl<'a, 'b, W: Write + 'a> Visitor<'b> for InfluxVisitor<'a, W> {
+    fn metric(&mut self, name: &dyn Name, value: &dyn Value) {
+        if self.error.is_some() {
+            return;
+        }
+        let mut parts = LineVisitor {
+            measurement_key: self.measurement_key,
+            .. LineVisitor::default()
+        };
+        name.visit(&mut parts);
+        parts.tags.sort();
+
+        let mut line = String::with_capacity(128);
+        escape(parts.measurement.as_ref().map(|x| &x[..]).unwrap_or("cantal"),
+               &[',', ' '], &mut line);
+        for (key, value) in &parts.tags {
+            line.push(',');
+            escape(key, &[',', '=', ' '], &mut line);
+            line.push('=');
+            escape(value, &[',', '=', ' '], &mut line);
+        }
+        line.push(' ');
+        escape(parts.field.as_ref().map(|x| &x[..]).unwrap_or("value"),
+               &[',', '=', ' '], &mut line);
+        line.push('=');
+        if !format_value(value, &mut line) {
+            return;
+        }
+        if let Err(e) = writeln!(self.out, "{} {}", line, self.timestamp) {
+            self.error = Some(e);
+        }
+    }
+}
+
+/// Write all metrics of a collection in InfluxDB line protocol
+///
+/// The value of `measurement_key` of the name is used as a measurement
+/// (`cantal` if there is no such key), the value of the `metric` key is
+/// used as a field name (`value` if absent) and all other keys are tags.
+/// For example, with `measurement_key` set to `group`, the name
+/// `{"group": "http", "metric": "requests", "status": "200"}` is written as
+/// `http,status=200 requests=10i 1500000000000000000`.
+///
+/// Every metric is written on its own line with timestamp in nanoseconds.
+pub fn influx<C, W>(col: &C, measurement_key: &str, time: SystemTime,
+    mut out: W)
+    -> io::Result<()>
+    where C: Collection + ?Sized,
+          W: Write,
+{
+    let timestamp = time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() * 1_000_000_000 + u64::from(d.subsec_nanos()))
+        .unwrap_or(0);
+    let mut visitor = InfluxVisitor {
+        out: &mut out,
+        measurement_key,
+        timestamp,
+        error: None,
+    };
+    col.visit(&mut visitor);
+    match visitor.error {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,186 @@
+use std::borrow::Cow;
+
+use collection::{Collection, Visitor};
+use name::{Name, NameVisitor};
+use value::Value;
+
+
+/// A collection that adds labels to every metric of the inner collection
+///
+/// This allows a library to export metrics without knowing where they are
+/// mounted in the application:
+///
+/// ```rust
+/// # extern crate libcantal;
+/// # #[macro_use] extern crate serde_json;
+/// # use libcantal::{Counter, Labeled, Value};
+/// # fn main() {
+/// # let requests = Counter::new();
+/// let lib_metrics = vec![
+///     (json!({"metric": "requests"}), &requests as &dyn Value),
+/// ];
+/// // exported as {"group": "http", "metric": "requests"}
+/// let metrics = Labeled::new(lib_metrics).label("group", "http");
+/// # }
+/// ```
+pub struct Labeled<C> {
+    inner: C,
+    labels: Vec<(String, String)>,
+    prefixes: Vec<(String, String)>,
+}
+
+struct LabelVisitor<'a, 'x: 'a> {
+    inner: &'a mut dyn Visitor<'x>,
+    labels: &'a [(String, String)],
+    prefixes: &'a [(String, String)],
+    prefixed: Vec<Option<String>>,
+}
+
+struct PrefixVisitor<'a> {
+    prefixes: &'a [(String, String)],
+    prefixed: &'a mut [Option<String>],
+}
+
+struct LabeledName<'a> {
+    inner: &'a dyn Name,
+    labels: &'a [(String, String)],
+    prefixes: &'a [(String, String)],
+    prefixed: &'a [Option<String>],
+}
+
+struct LabeledNameVisitor<'a> {
+    inner: &'a mut dyn NameVisitor,
+    labels: &'a [(String, String)],
+    prefixes: &'a [(String, String)],
+    prefixed: &'a [Option<String>],
+}
+
+impl<C> Labeled<C> {
+    /// Wrap a collection, use `label` and `prefix` to set up labels
+    pub fn new(inner: C) -> Labeled<C> {
+        Labeled {
+            inner,
+            labels: Vec::new(),
+            prefixes: Vec::new(),
+        }
+    }
+    /// Add a label to every metric
+    ///
+    /// If metric already has this key, the value is overridden.
+    pub fn label<K, V>(mut self, key: K, value: V) -> Labeled<C>
+        where K: Into<String>,
+              V: Into<String>,
+    {
+        let key = key.into();
+        self.labels.retain(|(k, _)| *k != key);
+        self.labels.push((key, value.into()));
+        self
+    }
+    /// Prepend `prefix` to the value of `key` of every metric
+    ///
+    /// Metrics that don't have such key are left intact. For example,
+    /// `prefix("metric", "http.")` turns `{"metric": "requests"}` into
+    /// `{"metric": "http.requests"}`.
+    pub fn prefix<K, V>(mut self, key: K, prefix: V) -> Labeled<C>
+        where K: Into<String>,
+              V: Into<String>,
+    {
+        let key = key.into();
+        self.prefixes.retain(|(k, _)| *k != key);
+        self.prefixes.push((key, prefix.into()));
+        self
+    }
+    /// Returns a reference to the inner collection
+    pub fn get_ref(&self) -> &C {
+        &self.inner
+    }
+    /// Unwraps the inner collection
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+}
+
+impl<'a> NameVisitor for PrefixVisitor<'a> {
+    fn visit_pair(&mut self, key: &str, value: &str) {
+        if let Some(idx) = self.prefixes.iter().position(|(k, _)| k == key) {
+            self.prefixed[idx] = Some(format!("{}{}", self.prefixes[idx].1,
+                                              value));
+        }
+    }
+}
+
+impl<'a> NameVisitor for LabeledNameVisitor<'a> {
+    fn visit_pair(&mut self, key: &str, value: &str) {
+        if self.labels.iter().any(|(k, _)| k == key) {
+            return;
+        }
+        match self.prefixes.iter().position(|(k, _)| k == key) {
+            Some(idx) => match self.prefixed[idx] {
+                Some(ref prefixed) => self.inner.visit_pair(key, prefixed),
+                None => self.inner.visit_pair(key, value),
+            },
+            None => self.inner.visit_pair(key, value),
+        }
+    }
+    fn visit_invalid(&mut self, key: &str) {
+        if !self.labels.iter().any(|(k, _)| k == key) {
+            self.inner.visit_invalid(key);
+        }
+    }
+}
+
+impl<'a> Name for LabeledName<'a> {
+    fn get(&self, key: &str) -> Option<Cow<'_, str>> {
+        if let Some((_, v)) = self.labels.iter().find(|(k, _)| k == key) {
+            return Some(v[..].into());
+        }
+        if let Some(idx) = self.prefixes.iter().position(|(k, _)| k == key) {
+            if let Some(ref prefixed) = self.prefixed[idx] {
+                return Some(prefixed[..].into());
+            }
+        }
+        self.inner.get(key)
+    }
+    fn visit(&self, s: &mut dyn NameVisitor) {
+        self.inner.visit(&mut LabeledNameVisitor {
+            inner: s,
+            labels: self.labels,
+            prefixes: self.prefixes,
+            prefixed: self.prefixed,
+        });
+        for (k, v) in self.labels {
+            s.visit_pair(k, v);
+        }
+    }
+}
+
+impl<'a, 'x: 'a> Visitor<'x> for LabelVisitor<'a, 'x> {
+    fn metric(&mut self, name: &dyn Name, value: &'x dyn Value) {
+        if !self.prefixes.is_empty() {
+            for item in &mut self.prefixed {
+                *item = None;
+            }
+            name.visit(&mut PrefixVisitor {
+                prefixes: self.prefixes,
+                prefixed: &mut self.prefixed,
+            });
+        }
+        self.inner.metric(&LabeledName {
+            inner: name,
+            labels: self.labels,
+            prefixes: self.prefixes,
+            prefixed: &self.prefixed,
+        }, value);
+    }
+}
+
+impl<C: Collection> Collection for Labeled<C> {
+    fn visit<'x>(&'x self, visitor: &mut dyn Visitor<'x>) {
+        self.inner.visit(&mut LabelVisitor {
+            inner: visitor,
+            labels: &self.labels,
+            prefixes: &self.prefixes,
+            prefixed: vec![None; self.prefixes.len()],
+        });
+    }
+}
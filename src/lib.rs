@@ -41,35 +41,98 @@
 //! ```
 #![warn(missing_docs)]
 extern crate atomic;
+extern crate lazy_static;
 extern crate libc;
 extern crate serde;
-extern crate serde_json;
+#[cfg_attr(test, macro_use)] extern crate serde_json;
 
 #[macro_use] extern crate log;
 #[macro_use] extern crate quick_error;
 
+mod channel;
 mod collection;
 mod collections;
+mod encode;
 mod error;
+mod filter;
+mod graphite;
+mod heartbeat;
+mod history;
+mod influx;
 mod json;
+mod labeled;
+mod lock;
+mod logger;
+mod macros;
 mod name;
 mod names;
+mod panic;
+mod path;
+mod periodic;
 mod print;
+#[cfg(target_os="linux")] mod process;
+mod prometheus;
 mod read;
+mod recorder;
+mod sharded;
+mod snapshot;
+mod state;
+mod statsd;
+mod time;
 mod value;
+mod watcher;
 
 mod counter;
+mod counting;
 mod integer;
 
+#[cfg(feature="allocator")] mod allocator;
+#[cfg(feature="http")] mod http;
+#[cfg(feature="registry")] mod registry;
+
+pub use channel::{QueueMetrics, counting_channel, counting_sync_channel};
+pub use channel::{CountingSender, CountingSyncSender, CountingReceiver};
+pub use channel::CountingIter;
 pub use collection::{Collection, Visitor, start};
 pub use counter::Counter;
+pub use counting::{CountingReader, CountingWriter};
+pub use encode::NameEncoder;
 pub use error::Error;
+pub use filter::{Filter, MapNames};
+pub use graphite::graphite;
+pub use heartbeat::{HeartbeatMetrics, HeartbeatGuard, heartbeat};
+pub use history::{History, HistoryRecorderGuard, Sample, record_history};
+pub use influx::influx;
 pub use integer::Integer;
 pub use json::Json;
-pub use name::{NameVisitor, Name};
+pub use labeled::Labeled;
+pub use lock::{LockMetrics, InstrumentedMutex, InstrumentedRwLock};
+pub use lock::InstrumentedMutexGuard;
+pub use lock::{InstrumentedReadGuard, InstrumentedWriteGuard};
+pub use logger::{LogMetrics, CountingLogger};
+pub use name::{NameVisitor, Name, visit_display, StaticName};
+pub use names::{Strict, Coerce};
+pub use panic::{PanicMetrics, install_panic_hook, panic_metrics};
 pub use print::print;
+#[cfg(target_os="linux")]
+pub use process::{ProcessMetrics, ProcessRefresherGuard, refresh_process};
+pub use prometheus::prometheus;
 pub use read::{start_with_reading};
+pub use recorder::{record, record_rotating, RecorderGuard};
+pub use sharded::{ShardedCounter, ShardedFlusherGuard, flush_sharded};
+pub use snapshot::{Snapshot, Metric, MetricValue};
+pub use state::State;
+pub use statsd::{push_statsd, StatsdPusherGuard};
 pub use value::{Value, RawType, LevelKind};
+pub use watcher::{Watcher, Rule, WatcherGuard, watch};
+#[doc(hidden)] pub use lazy_static::lazy_static as __lazy_static;
+#[cfg(feature="allocator")]
+pub use allocator::{CountingAlloc, AllocMetrics};
+#[cfg(feature="http")] pub use http::{serve_http, HttpServer};
+#[cfg(feature="registry")]
+pub use registry::{Registry, RegistryGuard, NAMESPACE_KEY};
+#[cfg(feature="registry")]
+pub use registry::{registry, register, start_registry};
 
 use std::path::PathBuf;
 
@@ -83,6 +146,7 @@ pub struct ActiveCollection<'a> {
     metrics: Vec<&'a Value>,
     mmap: *mut libc::c_void,
     mmap_size: usize,
+    retired: bool,
 }
 
 /// An active collection currently publishing metrics
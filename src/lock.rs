@@ -0,0 +1,276 @@
+use std::ops::{Deref, DerefMut};
+use std::sync::{Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::sync::{LockResult, PoisonError, TryLockError};
+use std::time::{Duration, Instant};
+
+use collection::{Collection, Visitor, visit_group};
+use counter::Counter;
+
+
+/// Metrics of a lock, updated by `InstrumentedMutex`/`InstrumentedRwLock`
+///
+/// Named `{"group": "lock", "lock": <name>, "metric": ..}` where metric is
+/// one of:
+///
+/// * `acquisitions` -- number of times lock was taken
+/// * `contended_acquisitions` -- number of times lock was taken by somebody
+///   else, so we had to wait
+/// * `wait_ns` -- total time spent waiting for the lock (nanoseconds)
+/// * `hold_ns` -- total time the lock was held (nanoseconds)
+///
+/// Read and write locks of a `RwLock` are counted together.
+pub struct LockMetrics {
+    name: String,
+    acquisitions: Counter,
+    contended_acquisitions: Counter,
+    wait_ns: Counter,
+    hold_ns: Counter,
+}
+
+/// A `Mutex` that tracks contention in `LockMetrics`
+pub struct InstrumentedMutex<T: ?Sized> {
+    metrics: LockMetrics,
+    inner: Mutex<T>,
+}
+
+/// A `RwLock` that tracks contention in `LockMetrics`
+pub struct InstrumentedRwLock<T: ?Sized> {
+    metrics: LockMetrics,
+    inner: RwLock<T>,
+}
+
+/// Guard of the `InstrumentedMutex`, hold time is counted when dropped
+pub struct InstrumentedMutexGuard<'a, T: ?Sized + 'a> {
+    guard: MutexGuard<'a, T>,
+    metrics: &'a LockMetrics,
+    acquired: Instant,
+}
+
+/// Read guard of the `InstrumentedRwLock`
+pub struct InstrumentedReadGuard<'a, T: ?Sized + 'a> {
+    guard: RwLockReadGuard<'a, T>,
+    metrics: &'a LockMetrics,
+    acquired: Instant,
+}
+
+/// Write guard of the `InstrumentedRwLock`
+pub struct InstrumentedWriteGuard<'a, T: ?Sized + 'a> {
+    guard: RwLockWriteGuard<'a, T>,
+    metrics: &'a LockMetrics,
+    acquired: Instant,
+}
+
+fn nanos(dur: Duration) -> u64 {
+    dur.as_secs() * 1_000_000_000 + dur.subsec_nanos() as u64
+}
+
+fn map_result<G, R, F>(result: LockResult<G>, f: F) -> LockResult<R>
+    where F: FnOnce(G) -> R,
+{
+    match result {
+        Ok(guard) => Ok(f(guard)),
+        Err(e) => Err(PoisonError::new(f(e.into_inner()))),
+    }
+}
+
+impl LockMetrics {
+    fn new(name: String) -> LockMetrics {
+        LockMetrics {
+            name,
+            acquisitions: Counter::new(),
+            contended_acquisitions: Counter::new(),
+            wait_ns: Counter::new(),
+            hold_ns: Counter::new(),
+        }
+    }
+    /// Try `try_lock` first, then fall back to blocking `lock`
+    fn acquire<G, T, L>(&self, try_lock: T, lock: L) -> LockResult<G>
+        where T: FnOnce() -> Result<G, TryLockError<G>>,
+              L: FnOnce() -> LockResult<G>,
+    {
+        self.acquisitions.incr(1);
+        match try_lock() {
+            Ok(guard) => Ok(guard),
+            Err(TryLockError::Poisoned(e)) => Err(e),
+            Err(TryLockError::WouldBlock) => {
+                self.contended_acquisitions.incr(1);
+                let start = Instant::now();
+                let result = lock();
+                self.wait_ns.incr(nanos(start.elapsed()));
+                result
+            }
+        }
+    }
+    fn release(&self, acquired: Instant) {
+        self.hold_ns.incr(nanos(acquired.elapsed()));
+    }
+    /// Returns the name of the lock
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    /// Returns number of acquisitions of the lock
+    pub fn acquisitions(&self) -> u64 {
+        self.acquisitions.get()
+    }
+    /// Returns number of acquisitions where we had to wait for the lock
+    pub fn contended_acquisitions(&self) -> u64 {
+        self.contended_acquisitions.get()
+    }
+}
+
+impl<T> InstrumentedMutex<T> {
+    /// Create a new mutex named `name`
+    pub fn new<S: Into<String>>(name: S, value: T) -> InstrumentedMutex<T> {
+        InstrumentedMutex {
+            metrics: LockMetrics::new(name.into()),
+            inner: Mutex::new(value),
+        }
+    }
+    /// Consume the mutex, returning the underlying data
+    pub fn into_inner(self) -> LockResult<T> {
+        self.inner.into_inner()
+    }
+}
+
+impl<T: ?Sized> InstrumentedMutex<T> {
+    /// Acquire the mutex, see `std::sync::Mutex::lock`
+    pub fn lock(&self) -> LockResult<InstrumentedMutexGuard<'_, T>> {
+        let result = self.metrics.acquire(
+            || self.inner.try_lock(), || self.inner.lock());
+        let metrics = &self.metrics;
+        map_result(result, |guard| InstrumentedMutexGuard {
+            guard,
+            metrics,
+            acquired: Instant::now(),
+        })
+    }
+    /// Returns a mutable reference to the underlying data
+    pub fn get_mut(&mut self) -> LockResult<&mut T> {
+        self.inner.get_mut()
+    }
+    /// Returns metrics of this lock
+    pub fn metrics(&self) -> &LockMetrics {
+        &self.metrics
+    }
+}
+
+impl<T> InstrumentedRwLock<T> {
+    /// Create a new lock named `name`
+    pub fn new<S: Into<String>>(name: S, value: T) -> InstrumentedRwLock<T> {
+        InstrumentedRwLock {
+            metrics: LockMetrics::new(name.into()),
+            inner: RwLock::new(value),
+        }
+    }
+    /// Consume the lock, returning the underlying data
+    pub fn into_inner(self) -> LockResult<T> {
+        self.inner.into_inner()
+    }
+}
+
+impl<T: ?Sized> InstrumentedRwLock<T> {
+    /// Lock for reading, see `std::sync::RwLock::read`
+    pub fn read(&self) -> LockResult<InstrumentedReadGuard<'_, T>> {
+        let result = self.metrics.acquire(
+            || self.inner.try_read(), || self.inner.read());
+        let metrics = &self.metrics;
+        map_result(result, |guard| InstrumentedReadGuard {
+            guard,
+            metrics,
+            acquired: Instant::now(),
+        })
+    }
+    /// Lock for writing, see `std::sync::RwLock::write`
+    pub fn write(&self) -> LockResult<InstrumentedWriteGuard<'_, T>> {
+        let result = self.metrics.acquire(
+            || self.inner.try_write(), || self.inner.write());
+        let metrics = &self.metrics;
+        map_result(result, |guard| InstrumentedWriteGuard {
+            guard,
+            metrics,
+            acquired: Instant::now(),
+        })
+    }
+    /// Returns a mutable reference to the underlying data
+    pub fn get_mut(&mut self) -> LockResult<&mut T> {
+        self.inner.get_mut()
+    }
+    /// Returns metrics of this lock
+    pub fn metrics(&self) -> &LockMetrics {
+        &self.metrics
+    }
+}
+
+impl<'a, T: ?Sized> Deref for InstrumentedMutexGuard<'a, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<'a, T: ?Sized> DerefMut for InstrumentedMutexGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.guard
+    }
+}
+
+impl<'a, T: ?Sized> Drop for InstrumentedMutexGuard<'a, T> {
+    fn drop(&mut self) {
+        self.metrics.release(self.acquired);
+    }
+}
+
+impl<'a, T: ?Sized> Deref for InstrumentedReadGuard<'a, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<'a, T: ?Sized> Drop for InstrumentedReadGuard<'a, T> {
+    fn drop(&mut self) {
+        self.metrics.release(self.acquired);
+    }
+}
+
+impl<'a, T: ?Sized> Deref for InstrumentedWriteGuard<'a, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<'a, T: ?Sized> DerefMut for InstrumentedWriteGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.guard
+    }
+}
+
+impl<'a, T: ?Sized> Drop for InstrumentedWriteGuard<'a, T> {
+    fn drop(&mut self) {
+        self.metrics.release(self.acquired);
+    }
+}
+
+impl Collection for LockMetrics {
+    fn visit<'x>(&'x self, visitor: &mut dyn Visitor<'x>) {
+        visit_group(visitor, "lock", Some(("lock", &self.name)), &[
+            ("acquisitions", &self.acquisitions),
+            ("contended_acquisitions", &self.contended_acquisitions),
+            ("wait_ns", &self.wait_ns),
+            ("hold_ns", &self.hold_ns),
+        ]);
+    }
+}
+
+impl<T: ?Sized> Collection for InstrumentedMutex<T> {
+    fn visit<'x>(&'x self, visitor: &mut dyn Visitor<'x>) {
+        self.metrics.visit(visitor)
+    }
+}
+
+impl<T: ?Sized> Collection for InstrumentedRwLock<T> {
+    fn visit<'x>(&'x self, visitor: &mut dyn Visitor<'x>) {
+        self.metrics.visit(visitor)
+    }
+}
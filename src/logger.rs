@@ -0,0 +1,156 @@
+use std::sync::Arc;
+
+use log::{Log, LogLevel, LogMetadata, LogRecord};
+
+use collection::{Collection, Visitor};
+use counter::Counter;
+
+
+const LEVELS: [&str; 5] = ["error", "warn", "info", "debug", "trace"];
+
+/// Counters of log records by level and (optionally) by target
+///
+/// Metrics are named `{"group": "log", "metric": "records", "level": ..}`,
+/// where level is one of `error`, `warn`, `info`, `debug` and `trace`.
+/// Counters per target prefix added with `target` have additional
+/// `target` key.
+///
+/// Use `CountingLogger` to update the counters.
+pub struct LogMetrics {
+    levels: [Counter; 5],
+    targets: Vec<(String, [Counter; 5])>,
+}
+
+/// A logger that counts records and passes them to the inner logger
+///
+/// Only records passing the maximum log level reach the logger, so
+/// they are the only ones counted, regardless of whether inner logger
+/// filters them out:
+///
+/// ```rust,no_run
+/// # extern crate libcantal;
+/// # extern crate log;
+/// # extern crate env_logger;
+/// # use std::sync::Arc;
+/// # use libcantal::{CountingLogger, LogMetrics};
+/// # fn main() {
+/// let metrics = Arc::new(LogMetrics::new().target("hyper"));
+/// let log_metrics = metrics.clone();
+/// log::set_logger(|max_level| {
+///     let logger = env_logger::LogBuilder::new().build();
+///     max_level.set(logger.filter());
+///     Box::new(CountingLogger::new(logger, log_metrics))
+/// }).expect("logger is not set yet");
+/// # }
+/// ```
+pub struct CountingLogger<L> {
+    inner: L,
+    metrics: Arc<LogMetrics>,
+}
+
+fn level_counters() -> [Counter; 5] {
+    [Counter::new(), Counter::new(), Counter::new(),
+     Counter::new(), Counter::new()]
+}
+
+fn level_index(level: LogLevel) -> usize {
+    match level {
+        LogLevel::Error => 0,
+        LogLevel::Warn => 1,
+        LogLevel::Info => 2,
+        LogLevel::Debug => 3,
+        LogLevel::Trace => 4,
+    }
+}
+
+fn matches(target: &str, prefix: &str) -> bool {
+    target.starts_with(prefix) &&
+        (target.len() == prefix.len() ||
+         target[prefix.len()..].starts_with("::"))
+}
+
+impl LogMetrics {
+    /// Create counters of records per level
+    pub fn new() -> LogMetrics {
+        LogMetrics {
+            levels: level_counters(),
+            targets: Vec::new(),
+        }
+    }
+    /// Also count records which target is `prefix` or its submodule
+    ///
+    /// For example, `target("hyper")` counts records of `hyper` and
+    /// `hyper::client` but not of `hyperlocal`. A record is counted in all
+    /// the targets it matches.
+    pub fn target<S: Into<String>>(mut self, prefix: S) -> LogMetrics {
+        let prefix = prefix.into();
+        if !self.targets.iter().any(|(t, _)| *t == prefix) {
+            self.targets.push((prefix, level_counters()));
+        }
+        self
+    }
+    /// Returns counter of records of the specified level
+    pub fn level(&self, level: LogLevel) -> &Counter {
+        &self.levels[level_index(level)]
+    }
+    fn count(&self, level: LogLevel, target: &str) {
+        let idx = level_index(level);
+        self.levels[idx].incr(1);
+        for (prefix, counters) in &self.targets {
+            if matches(target, prefix) {
+                counters[idx].incr(1);
+            }
+        }
+    }
+}
+
+impl Default for LogMetrics {
+    fn default() -> LogMetrics {
+        LogMetrics::new()
+    }
+}
+
+impl<L: Log> CountingLogger<L> {
+    /// Wrap a logger, records are counted in `metrics`
+    pub fn new(inner: L, metrics: Arc<LogMetrics>) -> CountingLogger<L> {
+        CountingLogger { inner, metrics }
+    }
+    /// Returns a reference to the inner logger
+    pub fn get_ref(&self) -> &L {
+        &self.inner
+    }
+}
+
+impl<L: Log> Log for CountingLogger<L> {
+    fn enabled(&self, metadata: &LogMetadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+    fn log(&self, record: &LogRecord) {
+        self.metrics.count(record.level(), record.target());
+        self.inner.log(record);
+    }
+}
+
+impl Collection for LogMetrics {
+    fn visit<'x>(&'x self, visitor: &mut dyn Visitor<'x>) {
+        for (level, counter) in LEVELS.iter().zip(self.levels.iter()) {
+            let name: &[(&str, &str)] = &[
+                ("group", "log"),
+                ("metric", "records"),
+                ("level", level),
+            ];
+            visitor.metric(&name, counter);
+        }
+        for (target, counters) in &self.targets {
+            for (level, counter) in LEVELS.iter().zip(counters.iter()) {
+                let name: &[(&str, &str)] = &[
+                    ("group", "log"),
+                    ("metric", "records"),
+                    ("level", level),
+                    ("target", target),
+                ];
+                visitor.metric(&name, counter);
+            }
+        }
+    }
+}
@@ -0,0 +1,83 @@
+/// Declare metrics and a function that returns their collection
+///
+/// Every metric is a `lazy_static` with a name attached, and the function
+/// lists all of them, so names and statics can't go out of sync:
+///
+/// ```rust
+/// #[macro_use] extern crate libcantal;
+/// use libcantal::{Counter, Integer, start};
+///
+/// metrics! {
+///     /// Metrics of the http server
+///     pub fn http_metrics() {
+///         /// Number of requests served
+///         pub static REQUESTS: Counter =
+///             {"group": "http", "metric": "requests"};
+///         static CONNECTIONS: Integer =
+///             {"group": "http", "metric": "connections"};
+///     }
+/// }
+///
+/// # fn main() {
+/// REQUESTS.incr(1);
+/// let metrics = http_metrics();
+/// let _coll = start(&metrics).expect("cantal works");
+/// # }
+/// ```
+///
+/// Metric types must have a `new()` constructor. The function returns
+/// a `Vec<(&'static [(&'static str, &'static str)], &'static dyn Value)>`
+/// which is a `Collection`.
+#[macro_export]
+macro_rules! metrics {
+    (@parse [$($done:tt)*]
+        $(#[$attr:meta])* pub ($($vis:tt)+) static $name:ident : $typ:ty
+            = $labels:tt; $($rest:tt)*) => {
+        $crate::metrics!(@parse [$($done)* ([$(#[$attr])*] [pub ($($vis)+)]
+            $name $typ $labels)] $($rest)*);
+    };
+    (@parse [$($done:tt)*]
+        $(#[$attr:meta])* pub static $name:ident : $typ:ty
+            = $labels:tt; $($rest:tt)*) => {
+        $crate::metrics!(@parse [$($done)* ([$(#[$attr])*] [pub]
+            $name $typ $labels)] $($rest)*);
+    };
+    (@parse [$($done:tt)*]
+        $(#[$attr:meta])* static $name:ident : $typ:ty
+            = $labels:tt; $($rest:tt)*) => {
+        $crate::metrics!(@parse [$($done)* ([$(#[$attr])*] []
+            $name $typ $labels)] $($rest)*);
+    };
+    (@parse [$(
+        ([$($attr:tt)*] [$($vis:tt)*] $name:ident $typ:ty
+            {$($key:tt : $value:tt),* $(,)*})
+    )*] @fn [$($fattr:tt)*] [$($fvis:tt)*] $fname:ident) => {
+        $(
+            $crate::__lazy_static! {
+                $($attr)* $($vis)* static ref $name: $typ = <$typ>::new();
+            }
+        )*
+        $($fattr)*
+        $($fvis)* fn $fname() -> Vec<(&'static [(&'static str, &'static str)],
+                                    &'static dyn $crate::Value)>
+        {
+            vec![$(
+                (&[$(($key, $value)),*] as &'static [_],
+                 &*$name as &'static dyn $crate::Value),
+            )*]
+        }
+    };
+    ($(#[$fattr:meta])* pub ($($fvis:tt)+) fn $fname:ident()
+        { $($body:tt)* }) => {
+        $crate::metrics!(@parse [] $($body)*
+            @fn [$(#[$fattr])*] [pub ($($fvis)+)] $fname);
+    };
+    ($(#[$fattr:meta])* pub fn $fname:ident() { $($body:tt)* }) => {
+        $crate::metrics!(@parse [] $($body)*
+            @fn [$(#[$fattr])*] [pub] $fname);
+    };
+    ($(#[$fattr:meta])* fn $fname:ident() { $($body:tt)* }) => {
+        $crate::metrics!(@parse [] $($body)*
+            @fn [$(#[$fattr])*] [] $fname);
+    };
+}
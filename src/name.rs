@@ -1,7 +1,20 @@
+use std::borrow::Cow;
+use std::fmt::{self, Display, Write};
+use std::str::from_utf8;
+
+
 /// A structure used to serialize `Name` objects to submit to agent
 pub trait NameVisitor {
     /// Report a keyword name and value used to demark a metric
     fn visit_pair(&mut self, key: &str, value: &str);
+    /// Report a key which value can't be represented as a string
+    ///
+    /// This is called instead of `visit_pair` by names that have such
+    /// values, i.e. by `serde_json::Value` wrapped into `Strict`.
+    /// Default implementation ignores the key.
+    fn visit_invalid(&mut self, key: &str) {
+        let _ = key;
+    }
 }
 
 
@@ -10,10 +23,77 @@ pub trait NameVisitor {
 /// You may use `HashMap<String, String>` or `BTreeMap<String, String>` for
 /// the name, but it might be more efficient to have a structure as a name
 /// and used static strings as key names instead.
+///
+/// Out of the box name is implemented for:
+///
+/// * `serde_json::Value` (only objects are useful, see `Strict` and
+///   `Coerce` for how non-string values are treated)
+/// * `BTreeMap` and `HashMap` with string-like keys and values
+/// * slices of pairs `[(K, V)]`, i.e. `&[("metric", "requests")]`
+/// * a single pair `(K, V)`, i.e. `("metric", "requests")`
+/// * `&T`, `Box<T>` and `Arc<T>` where `T` is a name
+///
+/// Keys may be visited in any order, they are sorted when metadata is
+/// written for the agent.
 pub trait Name {
     /// Get item by key
-    fn get(&self, key: &str) -> Option<&str>;
+    ///
+    /// Should return the same value that is reported by `visit`. It's
+    /// borrowed unless the value has to be formatted, i.e. numbers in
+    /// `Coerce` names.
+    fn get(&self, key: &str) -> Option<Cow<'_, str>>;
     /// Visit all keys in metric
     fn visit(&self, s: &mut NameVisitor);
 }
 
+
+/// A name made of static key-value pairs, used by `#[derive(Collection)]`
+///
+/// Pairs are expected to be sorted by key.
+#[doc(hidden)]
+pub struct StaticName(pub &'static [(&'static str, &'static str)]);
+
+impl Name for StaticName {
+    fn get(&self, key: &str) -> Option<Cow<'_, str>> {
+        self.0.iter().find(|&&(k, _)| k == key).map(|&(_, v)| v.into())
+    }
+    fn visit(&self, s: &mut dyn NameVisitor) {
+        for &(key, value) in self.0 {
+            s.visit_pair(key, value);
+        }
+    }
+}
+
+struct StackBuf {
+    buf: [u8; 64],
+    len: usize,
+}
+
+impl Write for StackBuf {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        if end > self.buf.len() {
+            return Err(fmt::Error);
+        }
+        self.buf[self.len..end].copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+/// Report a key-value pair where value is formatted with `Display`
+///
+/// Short values are formatted on the stack, so in the common case nothing
+/// is allocated. This is what `#[derive(Name)]` uses for non-string fields.
+pub fn visit_display(s: &mut dyn NameVisitor, key: &str,
+    value: &dyn Display)
+{
+    let mut buf = StackBuf { buf: [0; 64], len: 0 };
+    if write!(buf, "{}", value).is_ok() {
+        // only whole strings are copied, so it's always valid utf-8
+        s.visit_pair(key, from_utf8(&buf.buf[..buf.len])
+            .expect("valid utf-8"));
+    } else {
+        s.visit_pair(key, &value.to_string());
+    }
+}
@@ -1,21 +1,274 @@
+use std::borrow::{Borrow, Cow};
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, BuildHasher};
+use std::sync::Arc;
+
 use serde_json::Value;
 
-use name::{Name, NameVisitor};
+use name::{Name, NameVisitor, visit_display};
 
 
-impl Name for Value {
-    fn get(&self, key: &str) -> Option<&str> {
-        self.as_object()
-        .and_then(|x| x.get(key))
-        .and_then(|x| x.as_str())
-    }
-    fn visit(&self, s: &mut NameVisitor) {
-        if let Some(obj) = self.as_object() {
+/// A `serde_json::Value` name where non-string values are errors
+///
+/// By default (when `serde_json::Value` is used as a name directly) keys
+/// with numbers, booleans, nulls and arrays are skipped, which means two
+/// metrics may end up with the same name. Wrap the name into `Strict` to
+/// report such keys as invalid, so `start` and `start_with_reading` fail:
+///
+/// ```rust
+/// # #[macro_use] extern crate serde_json;
+/// # extern crate libcantal;
+/// # use libcantal::{Counter, Strict, Value, start};
+/// # fn main() {
+/// let requests = Counter::new();
+/// let metrics = vec![
+///     (Strict(json!({"metric": "requests", "shard": 3})),
+///      &requests as &dyn Value),
+/// ];
+/// assert!(start(&metrics).is_err());
+/// # }
+/// ```
+///
+/// In all modes nested objects are flattened into dotted keys, i.e.
+/// `{"http": {"method": "GET"}}` becomes `{"http.method": "GET"}`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Strict<T>(pub T);
+
+/// A `serde_json::Value` name where numbers and booleans are stringified
+///
+/// Like `Strict`, but `{"shard": 3}` is reported as `{"shard": "3"}`.
+/// Nulls and arrays are still reported as invalid.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Coerce<T>(pub T);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Policy {
+    Ignore,
+    Strict,
+    Coerce,
+}
+
+fn visit_flat(s: &mut dyn NameVisitor, policy: Policy,
+    key: &str, value: &Value)
+{
+    match *value {
+        Value::String(ref v) => s.visit_pair(key, v),
+        Value::Number(ref v) if policy == Policy::Coerce => {
+            visit_display(s, key, v);
+        }
+        Value::Bool(v) if policy == Policy::Coerce => {
+            s.visit_pair(key, if v { "true" } else { "false" });
+        }
+        Value::Object(ref obj) => {
             for (k, v) in obj {
-                if let Some(vs) = v.as_str() {
-                    s.visit_pair(k, vs);
-                }
+                visit_flat(s, policy, &format!("{}.{}", key, k), v);
             }
         }
+        _ if policy == Policy::Ignore => {}
+        _ => s.visit_invalid(key),
+    }
+}
+
+// the same as `visit_flat` but for a single value
+fn get_flat(policy: Policy, value: &Value) -> Option<Cow<'_, str>> {
+    match *value {
+        Value::String(ref v) => Some(v[..].into()),
+        Value::Number(ref v) if policy == Policy::Coerce => {
+            Some(v.to_string().into())
+        }
+        Value::Bool(v) if policy == Policy::Coerce => {
+            Some(if v { "true" } else { "false" }.into())
+        }
+        _ => None,
+    }
+}
+
+fn get<'a>(policy: Policy, name: &'a Value, key: &str)
+    -> Option<Cow<'a, str>>
+{
+    let obj = name.as_object()?;
+    if let Some(value) = obj.get(key) {
+        return get_flat(policy, value);
+    }
+    let mut parts = key.split('.');
+    let mut value = obj.get(parts.next()?)?;
+    for part in parts {
+        value = value.as_object()?.get(part)?;
+    }
+    get_flat(policy, value)
+}
+
+fn visit(policy: Policy, name: &Value, s: &mut dyn NameVisitor) {
+    if let Some(obj) = name.as_object() {
+        for (k, v) in obj {
+            visit_flat(s, policy, k, v);
+        }
+    }
+}
+
+impl Name for Value {
+    fn get(&self, key: &str) -> Option<Cow<'_, str>> {
+        get(Policy::Ignore, self, key)
+    }
+    fn visit(&self, s: &mut dyn NameVisitor) {
+        visit(Policy::Ignore, self, s)
+    }
+}
+
+impl<T: Borrow<Value>> Name for Strict<T> {
+    fn get(&self, key: &str) -> Option<Cow<'_, str>> {
+        get(Policy::Strict, self.0.borrow(), key)
+    }
+    fn visit(&self, s: &mut dyn NameVisitor) {
+        visit(Policy::Strict, self.0.borrow(), s)
+    }
+}
+
+impl<T: Borrow<Value>> Name for Coerce<T> {
+    fn get(&self, key: &str) -> Option<Cow<'_, str>> {
+        get(Policy::Coerce, self.0.borrow(), key)
+    }
+    fn visit(&self, s: &mut dyn NameVisitor) {
+        visit(Policy::Coerce, self.0.borrow(), s)
+    }
+}
+
+impl<K, V> Name for BTreeMap<K, V>
+    where K: Borrow<str> + Ord,
+          V: AsRef<str>,
+{
+    fn get(&self, key: &str) -> Option<Cow<'_, str>> {
+        BTreeMap::get(self, key).map(|v| v.as_ref().into())
+    }
+    fn visit(&self, s: &mut dyn NameVisitor) {
+        for (k, v) in self {
+            s.visit_pair(k.borrow(), v.as_ref());
+        }
+    }
+}
+
+impl<K, V, S> Name for HashMap<K, V, S>
+    where K: Borrow<str> + Hash + Eq,
+          V: AsRef<str>,
+          S: BuildHasher,
+{
+    fn get(&self, key: &str) -> Option<Cow<'_, str>> {
+        HashMap::get(self, key).map(|v| v.as_ref().into())
+    }
+    fn visit(&self, s: &mut dyn NameVisitor) {
+        // keys are sorted when name is encoded for the agent
+        for (k, v) in self {
+            s.visit_pair(k.borrow(), v.as_ref());
+        }
+    }
+}
+
+impl<K: AsRef<str>, V: AsRef<str>> Name for [(K, V)] {
+    fn get(&self, key: &str) -> Option<Cow<'_, str>> {
+        // the last one wins, the same as when encoding
+        self.iter().rev()
+            .find(|(k, _)| k.as_ref() == key)
+            .map(|(_, v)| v.as_ref().into())
+    }
+    fn visit(&self, s: &mut dyn NameVisitor) {
+        for (k, v) in self {
+            s.visit_pair(k.as_ref(), v.as_ref());
+        }
+    }
+}
+
+impl<K: AsRef<str>, V: AsRef<str>> Name for (K, V) {
+    fn get(&self, key: &str) -> Option<Cow<'_, str>> {
+        if self.0.as_ref() == key {
+            Some(self.1.as_ref().into())
+        } else {
+            None
+        }
+    }
+    fn visit(&self, s: &mut dyn NameVisitor) {
+        s.visit_pair(self.0.as_ref(), self.1.as_ref());
+    }
+}
+
+impl<T: Name + ?Sized> Name for &T {
+    fn get(&self, key: &str) -> Option<Cow<'_, str>> {
+        (**self).get(key)
+    }
+    fn visit(&self, s: &mut dyn NameVisitor) {
+        (**self).visit(s)
+    }
+}
+
+impl<T: Name + ?Sized> Name for Box<T> {
+    fn get(&self, key: &str) -> Option<Cow<'_, str>> {
+        (**self).get(key)
+    }
+    fn visit(&self, s: &mut dyn NameVisitor) {
+        (**self).visit(s)
+    }
+}
+
+impl<T: Name + ?Sized> Name for Arc<T> {
+    fn get(&self, key: &str) -> Option<Cow<'_, str>> {
+        (**self).get(key)
+    }
+    fn visit(&self, s: &mut dyn NameVisitor) {
+        (**self).visit(s)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use serde_json::Value;
+
+    use name::{Name, NameVisitor};
+    use super::Coerce;
+
+    struct Pairs(BTreeMap<String, String>);
+
+    impl NameVisitor for Pairs {
+        fn visit_pair(&mut self, key: &str, value: &str) {
+            self.0.insert(key.to_string(), value.to_string());
+        }
+    }
+
+    fn check_get(name: &dyn Name, expected: usize) {
+        let mut pairs = Pairs(BTreeMap::new());
+        name.visit(&mut pairs);
+        assert_eq!(pairs.0.len(), expected);
+        for (key, value) in &pairs.0 {
+            assert_eq!(name.get(key).as_deref(), Some(&value[..]),
+                       "key {}", key);
+        }
+    }
+
+    fn sample() -> Value {
+        json!({
+            "metric": "requests",
+            "shard": 3,
+            "ratio": 0.5,
+            "enabled": true,
+            "http": {"method": "GET", "version": 2},
+            "missing": null,
+        })
+    }
+
+    #[test]
+    fn coerce_get_matches_visit() {
+        let name = Coerce(sample());
+        check_get(&name, 6);
+        assert_eq!(name.get("shard").as_deref(), Some("3"));
+        assert_eq!(name.get("http.version").as_deref(), Some("2"));
+        assert_eq!(name.get("missing"), None);
+    }
+
+    #[test]
+    fn ignore_get_matches_visit() {
+        let name = sample();
+        check_get(&name, 2);
+        assert_eq!(Name::get(&name, "http.method").as_deref(), Some("GET"));
+        assert_eq!(Name::get(&name, "shard"), None);
     }
 }
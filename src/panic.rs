@@ -0,0 +1,82 @@
+use std::panic;
+use std::sync::Once;
+use std::thread;
+
+use lazy_static::lazy_static;
+
+use collection::{Collection, Visitor};
+use counter::Counter;
+use state::State;
+
+
+/// Metrics updated by the panic hook, see `install_panic_hook`
+///
+/// Named `{"group": "process", "metric": "panics"}` (a counter) and
+/// `{"group": "process", "metric": "last_panic"}` (a state, like
+/// `thread 'worker' at src/main.rs:10`).
+pub struct PanicMetrics {
+    panics: Counter,
+    last_panic: State,
+}
+
+lazy_static! {
+    static ref PANIC_METRICS: PanicMetrics = PanicMetrics {
+        panics: Counter::new(),
+        last_panic: State::new(),
+    };
+}
+
+static INSTALL: Once = Once::new();
+
+/// Install a panic hook that counts panics
+///
+/// The hook records the panic in `panic_metrics()` and then calls the
+/// hook that was installed before. Installing it multiple times is no-op.
+/// Returns metrics to add to a collection.
+pub fn install_panic_hook() -> &'static PanicMetrics {
+    INSTALL.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            let thread = thread::current();
+            let thread = thread.name().unwrap_or("<unnamed>");
+            let state = match info.location() {
+                Some(loc) => format!("thread '{}' at {}:{}",
+                                     thread, loc.file(), loc.line()),
+                None => format!("thread '{}'", thread),
+            };
+            PANIC_METRICS.panics.incr(1);
+            PANIC_METRICS.last_panic.set(&state);
+            previous(info);
+        }));
+    });
+    &PANIC_METRICS
+}
+
+/// Returns metrics updated by the panic hook
+///
+/// Metrics are only updated after `install_panic_hook` is called.
+pub fn panic_metrics() -> &'static PanicMetrics {
+    &PANIC_METRICS
+}
+
+impl PanicMetrics {
+    /// Returns number of panics since the hook is installed
+    pub fn panics(&self) -> u64 {
+        self.panics.get()
+    }
+    /// Returns description of the last panic or empty string
+    pub fn last_panic(&self) -> String {
+        self.last_panic.get()
+    }
+}
+
+impl Collection for PanicMetrics {
+    fn visit<'x>(&'x self, visitor: &mut dyn Visitor<'x>) {
+        let name: &[(&str, &str)] =
+            &[("group", "process"), ("metric", "panics")];
+        visitor.metric(&name, &self.panics);
+        let name: &[(&str, &str)] =
+            &[("group", "process"), ("metric", "last_panic")];
+        visitor.metric(&name, &self.last_panic);
+    }
+}
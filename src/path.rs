@@ -0,0 +1,38 @@
+use name::{Name, NameVisitor};
+
+
+struct PairsVisitor<'a>(&'a mut Vec<(String, String)>);
+
+impl<'a> NameVisitor for PairsVisitor<'a> {
+    fn visit_pair(&mut self, key: &str, value: &str) {
+        self.0.push((key.to_string(), value.to_string()));
+    }
+}
+
+/// Build a dotted path (i.e. `http.requests.200`) out of the name
+///
+/// Values of the keys listed in `order` go first in that order, values of
+/// all other keys follow sorted by key. Characters that have special meaning
+/// in dotted paths are replaced by underscores.
+pub fn dotted_path(name: &dyn Name, order: &[&str], buf: &mut String) {
+    let mut pairs = Vec::new();
+    name.visit(&mut PairsVisitor(&mut pairs));
+    pairs.sort_by(|a, b| {
+        let apos = order.iter().position(|k| *k == a.0)
+            .unwrap_or(order.len());
+        let bpos = order.iter().position(|k| *k == b.0)
+            .unwrap_or(order.len());
+        (apos, &a.0).cmp(&(bpos, &b.0))
+    });
+    for (idx, (_, value)) in pairs.iter().enumerate() {
+        if idx > 0 {
+            buf.push('.');
+        }
+        for c in value.chars() {
+            match c {
+                'a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '-' => buf.push(c),
+                _ => buf.push('_'),
+            }
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use std::io;
+use std::sync::mpsc::{channel, Sender, RecvTimeoutError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+
+/// A guard of a background thread that runs a task periodically
+///
+/// Dropping the guard stops the thread and waits for it to finish.
+pub struct Periodic {
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+/// Run `task` every `interval` in a new thread named `name`
+///
+/// If `run_on_exit` is true the task is run one more time when the guard
+/// is dropped, which is useful for flushing data.
+pub fn spawn<F>(name: &str, interval: Duration, run_on_exit: bool, mut task: F)
+    -> io::Result<Periodic>
+    where F: FnMut() + Send + 'static
+{
+    let (tx, rx) = channel();
+    let thread = thread::Builder::new()
+        .name(name.into())
+        .spawn(move || {
+            let mut deadline = Instant::now() + interval;
+            loop {
+                let now = Instant::now();
+                let timeout = if deadline > now {
+                    deadline - now
+                } else {
+                    Duration::new(0, 0)
+                };
+                match rx.recv_timeout(timeout) {
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Ok(()) | Err(RecvTimeoutError::Disconnected) => break,
+                }
+                task();
+                deadline += interval;
+                let now = Instant::now();
+                if deadline < now {
+                    // we're too slow, don't try to catch up
+                    deadline = now + interval;
+                }
+            }
+            if run_on_exit {
+                task();
+            }
+        })?;
+    Ok(Periodic {
+        stop: Some(tx),
+        thread: Some(thread),
+    })
+}
+
+impl Drop for Periodic {
+    fn drop(&mut self) {
+        self.stop.take();
+        if let Some(thread) = self.thread.take() {
+            thread.join().map_err(|_| {
+                error!("Periodic metrics thread panicked");
+            }).ok();
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use std::fs::{File, read_dir};
+use std::io::{self, Read};
+use std::sync::Arc;
+use std::time::Duration;
+
+use libc;
+
+use collection::{Collection, Visitor, visit_group};
+use counter::Counter;
+use integer::Integer;
+use periodic::{Periodic, spawn};
+
+
+/// Resource usage of the current process read from `/proc/self`
+///
+/// Metrics are updated by `refresh` (or periodically by
+/// `refresh_process`) and are named `{"group": "process", "metric": ..}`:
+///
+/// * `rss_bytes`, `virtual_bytes` -- memory usage (levels)
+/// * `cpu_user_ticks`, `cpu_system_ticks` -- CPU time in clock ticks
+/// * `threads`, `open_fds` -- number of threads and file descriptors
+/// * `read_bytes`, `written_bytes` -- bytes passed through `read`- and
+///   `write`-like system calls (`rchar` and `wchar` in `/proc/self/io`)
+/// * `voluntary_context_switches`, `involuntary_context_switches`
+///
+/// Only available on linux.
+pub struct ProcessMetrics {
+    rss_bytes: Integer,
+    virtual_bytes: Integer,
+    cpu_user_ticks: Counter,
+    cpu_system_ticks: Counter,
+    threads: Integer,
+    open_fds: Integer,
+    read_bytes: Counter,
+    written_bytes: Counter,
+    voluntary_context_switches: Counter,
+    involuntary_context_switches: Counter,
+}
+
+/// A guard of the thread started by `refresh_process`
+///
+/// When dropped, the thread is stopped.
+pub struct ProcessRefresherGuard {
+    _periodic: Periodic,
+}
+
+fn read_file(path: &str, buf: &mut String) -> io::Result<()> {
+    buf.clear();
+    File::open(path)?.read_to_string(buf)?;
+    Ok(())
+}
+
+fn invalid(path: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData,
+                   format!("can't parse {}", path))
+}
+
+// Kernel reports ever-increasing values, so we add the difference
+fn set_counter(counter: &Counter, value: u64) {
+    counter.incr(value.saturating_sub(counter.get()));
+}
+
+fn parse_field(path: &str, line: &str, key: &str) -> Option<u64> {
+    if line.starts_with(key) && line[key.len()..].starts_with(':') {
+        let value = line[key.len()+1..].trim().parse().ok();
+        if value.is_none() {
+            debug!("Bad value of {:?} in {}", key, path);
+        }
+        value
+    } else {
+        None
+    }
+}
+
+impl ProcessMetrics {
+    /// Create process metrics, call `refresh` to fill them in
+    pub fn new() -> ProcessMetrics {
+        ProcessMetrics {
+            rss_bytes: Integer::new(),
+            virtual_bytes: Integer::new(),
+            cpu_user_ticks: Counter::new(),
+            cpu_system_ticks: Counter::new(),
+            threads: Integer::new(),
+            open_fds: Integer::new(),
+            read_bytes: Counter::new(),
+            written_bytes: Counter::new(),
+            voluntary_context_switches: Counter::new(),
+            involuntary_context_switches: Counter::new(),
+        }
+    }
+    /// Read current values from `/proc/self`
+    ///
+    /// All the files are read even if some of them fail, the first error
+    /// is returned. Metrics should be refreshed from a single thread.
+    pub fn refresh(&self) -> io::Result<()> {
+        let mut buf = String::with_capacity(1024);
+        let stat = self.read_stat(&mut buf);
+        let status = self.read_status(&mut buf);
+        let io = self.read_io(&mut buf);
+        let fds = self.read_fds();
+        stat.and(status).and(io).and(fds)
+    }
+    fn read_stat(&self, buf: &mut String) -> io::Result<()> {
+        const PATH: &str = "/proc/self/stat";
+        read_file(PATH, buf)?;
+        // command name may contain spaces and parenthesis, so we skip
+        // everything up to the last parenthesis; first field after it is
+        // the third one in the proc(5) man page
+        let tail = buf.rfind(')').map(|idx| &buf[idx+1..])
+            .ok_or_else(|| invalid(PATH))?;
+        let fields = tail.split_whitespace().collect::<Vec<_>>();
+        let field = |num: usize| -> io::Result<u64> {
+            fields.get(num - 3).and_then(|x| x.parse().ok())
+                .ok_or_else(|| invalid(PATH))
+        };
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as u64;
+        set_counter(&self.cpu_user_ticks, field(14)?);
+        set_counter(&self.cpu_system_ticks, field(15)?);
+        self.threads.set(field(20)? as i64);
+        self.virtual_bytes.set(field(23)? as i64);
+        self.rss_bytes.set((field(24)? * page_size) as i64);
+        Ok(())
+    }
+    fn read_status(&self, buf: &mut String) -> io::Result<()> {
+        const PATH: &str = "/proc/self/status";
+        read_file(PATH, buf)?;
+        for line in buf.lines() {
+            if let Some(x) = parse_field(PATH, line,
+                "voluntary_ctxt_switches")
+            {
+                set_counter(&self.voluntary_context_switches, x);
+            } else if let Some(x) = parse_field(PATH, line,
+                "nonvoluntary_ctxt_switches")
+            {
+                set_counter(&self.involuntary_context_switches, x);
+            }
+        }
+        Ok(())
+    }
+    fn read_io(&self, buf: &mut String) -> io::Result<()> {
+        const PATH: &str = "/proc/self/io";
+        read_file(PATH, buf)?;
+        for line in buf.lines() {
+            if let Some(x) = parse_field(PATH, line, "rchar") {
+                set_counter(&self.read_bytes, x);
+            } else if let Some(x) = parse_field(PATH, line, "wchar") {
+                set_counter(&self.written_bytes, x);
+            }
+        }
+        Ok(())
+    }
+    fn read_fds(&self) -> io::Result<()> {
+        let mut count = 0;
+        for entry in read_dir("/proc/self/fd")? {
+            entry?;
+            count += 1;
+        }
+        // don't count descriptor of the directory being read
+        self.open_fds.set(count - 1);
+        Ok(())
+    }
+}
+
+impl Default for ProcessMetrics {
+    fn default() -> ProcessMetrics {
+        ProcessMetrics::new()
+    }
+}
+
+impl Collection for ProcessMetrics {
+    fn visit<'x>(&'x self, visitor: &mut dyn Visitor<'x>) {
+        visit_group(visitor, "process", None, &[
+            ("rss_bytes", &self.rss_bytes),
+            ("virtual_bytes", &self.virtual_bytes),
+            ("cpu_user_ticks", &self.cpu_user_ticks),
+            ("cpu_system_ticks", &self.cpu_system_ticks),
+            ("threads", &self.threads),
+            ("open_fds", &self.open_fds),
+            ("read_bytes", &self.read_bytes),
+            ("written_bytes", &self.written_bytes),
+            ("voluntary_context_switches",
+                &self.voluntary_context_switches),
+            ("involuntary_context_switches",
+                &self.involuntary_context_switches),
+        ]);
+    }
+}
+
+/// Refresh process metrics every `interval` in a background thread
+///
+/// Metrics are refreshed immediately, and errors of refreshing are logged.
+pub fn refresh_process(metrics: Arc<ProcessMetrics>, interval: Duration)
+    -> io::Result<ProcessRefresherGuard>
+{
+    metrics.refresh()
+        .map_err(|e| warn!("Error reading process metrics: {}", e)).ok();
+    let periodic = spawn("cantal-process", interval, false, move || {
+        metrics.refresh()
+            .map_err(|e| debug!("Error reading process metrics: {}", e))
+            .ok();
+    })?;
+    Ok(ProcessRefresherGuard {
+        _periodic: periodic,
+    })
+}
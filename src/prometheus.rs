@@ -0,0 +1,123 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as FmtWrite;
+use std::io::{self, Write};
+
+use collection::{Collection, Visitor};
+use name::{Name, NameVisitor};
+use value::{Value, RawType};
+
+
+struct Family {
+    kind: &'static str,
+    samples: Vec<String>,
+}
+
+struct PrometheusVisitor<'a>(&'a mut BTreeMap<String, Family>);
+
+struct LabelVisitor<'a> {
+    metric: Option<String>,
+    labels: &'a mut String,
+}
+
+fn sanitize(value: &str, allow_colon: bool, buf: &mut String) {
+    for (idx, c) in value.chars().enumerate() {
+        match c {
+            'a'..='z' | 'A'..='Z' | '_' => buf.push(c),
+            ':' if allow_colon => buf.push(c),
+            '0'..='9' => {
+                if idx == 0 {
+                    buf.push('_');
+                }
+                buf.push(c);
+            }
+            _ => buf.push('_'),
+        }
+    }
+}
+
+fn escape(value: &str, buf: &mut String) {
+    for c in value.chars() {
+        match c {
+            '\\' => buf.push_str("\\\\"),
+            '"' => buf.push_str("\\\""),
+            '\n' => buf.push_str("\\n"),
+            _ => buf.push(c),
+        }
+    }
+}
+
+impl<'a> NameVisitor for LabelVisitor<'a> {
+    fn visit_pair(&mut self, key: &str, value: &str) {
+        if key == "metric" {
+            let mut name = String::with_capacity(value.len());
+            sanitize(value, true, &mut name);
+            self.metric = Some(name);
+            return;
+        }
+        if !self.labels.is_empty() {
+            self.labels.push(',');
+        }
+        sanitize(key, false, self.labels);
+        self.labels.push_str("=\"");
+        escape(value, self.labels);
+        self.labels.push('"');
+    }
+}
+
+impl<'a, 'b> Visitor<'b> for PrometheusVisitor<'a> {
+    fn metric(&mut self, name: &dyn Name, value: &dyn Value) {
+        let kind = match value.raw_type() {
+            RawType::Counter => "counter",
+            RawType::Level(_) => "gauge",
+            // prometheus has no string values
+            RawType::State => return,
+        };
+        let mut labels = String::new();
+        let metric = {
+            let mut visitor = LabelVisitor {
+                metric: None,
+                labels: &mut labels,
+            };
+            name.visit(&mut visitor);
+            match visitor.metric {
+                Some(metric) => metric,
+                None => return,
+            }
+        };
+        let family = self.0.entry(metric.clone()).or_insert_with(|| Family {
+            kind,
+            samples: Vec::new(),
+        });
+        if family.kind != kind {
+            debug!("Metric {:?} is exported with different types, \
+                    skipping {} one", metric, kind);
+            return;
+        }
+        let mut line = metric;
+        if !labels.is_empty() {
+            write!(line, "{{{}}}", labels).expect("can always write");
+        }
+        write!(line, " {}", value.as_json()).expect("can always write");
+        family.samples.push(line);
+    }
+}
+
+/// Write all metrics of a collection in prometheus text format
+///
+/// The value of the `metric` key is used as a metric name and all other
+/// keys become labels. Metrics without a `metric` key and state values are
+/// skipped, as they have no representation in prometheus.
+pub fn prometheus<C, W>(col: &C, mut out: W) -> io::Result<()>
+    where C: Collection + ?Sized,
+          W: Write,
+{
+    let mut families = BTreeMap::new();
+    col.visit(&mut PrometheusVisitor(&mut families));
+    for (name, family) in &families {
+        writeln!(out, "# TYPE {} {}", name, family.kind)?;
+        for line in &family.samples {
+            writeln!(out, "{}", line)?;
+        }
+    }
+    Ok(())
+}
@@ -4,8 +4,7 @@ use std::io::{self, BufRead, BufReader};
 use std::fs::{File, OpenOptions};
 
 use libc;
-use serde_json::{to_string, to_value};
-use json::JsonName;
+use encode::NameEncoder;
 
 use collection::{start};
 
@@ -113,27 +112,17 @@ fn read_and_map<'x, T: Collection + ?Sized>(coll: &'x T)
         metrics: Vec::new(),
         mmap: ptr,
         mmap_size: values_size,
+        retired: false,
     };
 
 
-    struct MapVisitor<'a, 'b: 'a>(&'a mut HashMap<String, &'b Value>);
-    impl<'a, 'b: 'a> Visitor<'b> for MapVisitor<'a, 'b> {
-        fn metric(&mut self, name: &Name, value: &'b Value)
-        {
-            // we encode to_value first to get keys sorted
-            self.0.insert(to_string(&to_value(JsonName(name))
-                    .expect("can always serialize"))
-                    .expect("can always serialize"),
-                    value);
-        }
+    struct Slot {
+        offset: isize,
+        size: usize,
+        kind: String,
+        suffix: Option<String>,
     }
-
-    let mut map = HashMap::new();
-    coll.visit(&mut MapVisitor(&mut map));
-
-    let mut extra = 0;
-    let mut wrong_type = 0;
-
+    let mut slots = HashMap::new();
     let mut offset = 0;
     for line in BufReader::new(meta_file).lines() {
         let line = line.map_err(|e| ErrorEnum::Read(meta_path.clone(), e))?;
@@ -150,34 +139,88 @@ fn read_and_map<'x, T: Collection + ?Sized>(coll: &'x T)
             return Err(ErrorEnum::InvalidMeta(
                 meta_path.clone(), "Offset is out of range").into());
         }
-        if kind == "pad" {
-            offset += size;
-            continue;
+        if kind != "pad" {
+            let name = pair.next()
+                .ok_or_else(|| ErrorEnum::InvalidMeta(meta_path.clone(),
+                                                  "No description for value"))?
+                .trim();
+            slots.insert(name.to_string(), Slot {
+                offset,
+                size: size as usize,
+                kind: kind.to_string(),
+                suffix: suffix.map(|x| x.to_string()),
+            });
         }
-        let name = pair.next()
-            .ok_or_else(|| ErrorEnum::InvalidMeta(meta_path.clone(),
-                                              "No description for value"))?
-            .trim();
-        if let Some(metric) = map.remove(name) {
-            let typ = metric.raw_type();
-            if kind != typ.main_type() || suffix != typ.type_suffix() {
-                wrong_type += 1;
-            } else {
-                unsafe {
-                    metric.assign(ptr.offset(offset));
-                    result.metrics.push(metric);
+        offset += size;
+    }
+
+    struct MapVisitor<'a, 'b: 'a> {
+        slots: &'a mut HashMap<String, Slot>,
+        metrics: &'a mut Vec<&'b dyn Value>,
+        ptr: *mut libc::c_void,
+        encoder: NameEncoder,
+        buf: String,
+        missing: usize,
+        wrong_type: usize,
+        invalid: bool,
+    }
+    impl<'a, 'b: 'a> Visitor<'b> for MapVisitor<'a, 'b> {
+        fn metric(&mut self, name: &dyn Name, value: &'b dyn Value)
+        {
+            self.buf.clear();
+            self.encoder.encode(name, &mut self.buf);
+            if self.encoder.invalid_key().is_some() {
+                self.invalid = true;
+                return;
+            }
+            match self.slots.remove(&self.buf) {
+                Some(slot) => {
+                    let typ = value.raw_type();
+                    // size differs for values of variable size, i.e. `State`
+                    if slot.kind != typ.main_type() ||
+                        slot.size != value.raw_size() ||
+                        slot.suffix.as_ref().map(|x| &x[..])
+                            != typ.type_suffix()
+                    {
+                        self.wrong_type += 1;
+                    } else {
+                        unsafe {
+                            value.assign(self.ptr.offset(slot.offset));
+                        }
+                        self.metrics.push(value);
+                    }
                 }
+                None => self.missing += 1,
             }
-        } else {
-            extra += 1;
         }
-        offset += size;
     }
-    if extra > 0 || map.len() > 0 {
+
+    let (missing, wrong_type, invalid_name) = {
+        let mut visitor = MapVisitor {
+            slots: &mut slots,
+            metrics: &mut result.metrics,
+            ptr,
+            encoder: NameEncoder::new(),
+            buf: String::with_capacity(256),
+            missing: 0,
+            wrong_type: 0,
+            invalid: false,
+        };
+        coll.visit(&mut visitor);
+        (visitor.missing, visitor.wrong_type, visitor.invalid)
+    };
+    if invalid_name {
+        // `start` returns the error before touching the file, and the file
+        // is still valid for whoever wrote it, so don't remove it
+        result.retired = true;
+        return Ok(None);
+    }
+    let extra = slots.len();
+    if extra > 0 || missing > 0 || wrong_type > 0 {
         debug!("Found {} extra metrics, {} metrics are not present, \
                 {} have different type. \
                 Copying metrics and overriding file.",
-               extra, map.len(), wrong_type);
+               extra, missing, wrong_type);
         return Ok(None)
     } else {
         debug!("Continuing with {} metrics and {}/{} bytes",
@@ -185,3 +228,91 @@ fn read_and_map<'x, T: Collection + ?Sized>(coll: &'x T)
     }
     Ok(Some(result))
 }
+
+#[cfg(all(test, unix))]
+mod test {
+    use std::env;
+    use std::fs::remove_file;
+    use std::mem;
+    use std::path::PathBuf;
+    use std::sync::Mutex;
+
+    use lazy_static::lazy_static;
+
+    use {Counter, State, Strict, Value};
+    use {start_with_reading};
+
+    lazy_static! {
+        // tests change CANTAL_PATH, so they can't run in parallel
+        static ref LOCK: Mutex<()> = Mutex::new(());
+    }
+
+    type Pairs<'a> = &'a [(&'a str, &'a str)];
+
+    fn set_path(test: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("cantal-test-{}-{}",
+            test, ::std::process::id()));
+        env::set_var("CANTAL_PATH", &path);
+        path
+    }
+
+    fn cleanup(path: PathBuf) {
+        remove_file(path.with_extension("values")).ok();
+        remove_file(path.with_extension("meta")).ok();
+    }
+
+    fn run(capacity: usize) -> (State, Counter) {
+        let state = State::with_capacity(capacity);
+        let counter = Counter::new();
+        {
+            let metrics: &[(Pairs, &dyn Value)] = &[
+                (&[("metric", "state")], &state),
+                (&[("metric", "counter")], &counter),
+            ];
+            let coll = start_with_reading(metrics).expect("cantal works");
+            state.set("xxxxxxxxxxxxxxxx");
+            counter.incr(5);
+            // emulate `execve`, when destructors don't run
+            mem::forget(coll);
+        }
+        (state, counter)
+    }
+
+    #[test]
+    fn state_capacity_changed() {
+        let _lock = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let path = set_path("capacity");
+
+        let (_, counter) = run(8);
+        assert_eq!(counter.get(), 5);
+        // same layout, values are read back
+        let (state, counter) = run(8);
+        assert_eq!(counter.get(), 10);
+        assert_eq!(state.get(), "xxxxxxxx");
+        // state is larger now, so file is laid out again (values are
+        // copied), previously state was overlapping counter here
+        let (state, counter) = run(64);
+        assert_eq!(counter.get(), 15);
+        assert_eq!(state.get(), "xxxxxxxxxxxxxxxx");
+
+        cleanup(path);
+    }
+
+    #[test]
+    fn invalid_name() {
+        let _lock = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let path = set_path("invalid");
+        let counter = Counter::new();
+        let good = vec![(json!({"metric": "x"}), &counter as &dyn Value)];
+        mem::forget(start_with_reading(&good).expect("cantal works"));
+        // the same name as above when invalid key is dropped
+        let bad = vec![(Strict(json!({"metric": "x", "shard": null})),
+                        &counter as &dyn Value)];
+        let err = start_with_reading(&bad).err().expect("invalid name");
+        assert_eq!(err.to_string(),
+            r#"metric {"metric":"x"} has non-string value for key "shard""#);
+        assert!(path.with_extension("meta").exists());
+
+        cleanup(path);
+    }
+}
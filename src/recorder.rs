@@ -0,0 +1,163 @@
+use std::fs::{File, OpenOptions, rename, remove_file};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::ser::{Serialize, Serializer, SerializeMap};
+use serde_json::to_writer;
+
+use collection::Collection;
+use json::Json;
+use periodic::{spawn, Periodic};
+use time::now_ms;
+
+
+/// A guard of the thread started by `record` or `record_rotating`
+///
+/// When dropped, the last snapshot is written and the thread is stopped.
+pub struct RecorderGuard {
+    _periodic: Periodic,
+}
+
+struct Rotation {
+    max_size: u64,
+    keep: usize,
+}
+
+struct Writer {
+    path: PathBuf,
+    file: Option<File>,
+    size: u64,
+    rotation: Option<Rotation>,
+    buf: Vec<u8>,
+}
+
+struct Line<'a, C: Collection + ?Sized + 'a> {
+    timestamp: u64,
+    metrics: Json<'a, C>,
+}
+
+impl<'a, C: Collection + ?Sized + 'a> Serialize for Line<'a, C> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        let mut map = serializer.serialize_map(Some(2))?;
+        map.serialize_entry("timestamp", &self.timestamp)?;
+        map.serialize_entry("metrics", &self.metrics)?;
+        map.end()
+    }
+}
+
+fn open(path: &Path) -> io::Result<(File, u64)> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let size = file.metadata()?.len();
+    Ok((file, size))
+}
+
+fn rotated(path: &Path, idx: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(format!(".{}", idx));
+    PathBuf::from(name)
+}
+
+impl Writer {
+    fn write<C: Collection + ?Sized>(&mut self, coll: &C) {
+        let timestamp = now_ms();
+        self.buf.clear();
+        to_writer(&mut self.buf, &Line {
+            timestamp,
+            metrics: Json(coll),
+        }).expect("can always serialize");
+        self.buf.push(b'\n');
+        if let Err(e) = self.write_buf() {
+            error!("Can't write metrics to {:?}: {}", self.path, e);
+            // will try to reopen on next write
+            self.file = None;
+        }
+    }
+    fn write_buf(&mut self) -> io::Result<()> {
+        let len = self.buf.len() as u64;
+        let rotate = match self.rotation {
+            Some(ref r) => self.size > 0 && self.size + len > r.max_size,
+            None => false,
+        };
+        if rotate {
+            self.rotate()?;
+        }
+        if self.file.is_none() {
+            let (file, size) = open(&self.path)?;
+            self.file = Some(file);
+            self.size = size;
+        }
+        self.file.as_mut().expect("file is open").write_all(&self.buf)?;
+        self.size += len;
+        Ok(())
+    }
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file = None;
+        self.size = 0;
+        let keep = self.rotation.as_ref().map(|r| r.keep).unwrap_or(0);
+        if keep == 0 {
+            return remove_file(&self.path);
+        }
+        for idx in (1..keep).rev() {
+            match rename(rotated(&self.path, idx), rotated(&self.path, idx+1)) {
+                Ok(()) => {}
+                Err(ref e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+        }
+        rename(&self.path, rotated(&self.path, 1))
+    }
+}
+
+fn start<P, C>(path: P, interval: Duration, rotation: Option<Rotation>,
+    coll: Arc<C>)
+    -> io::Result<RecorderGuard>
+    where P: AsRef<Path>,
+          C: Collection + Send + Sync + ?Sized + 'static,
+{
+    let path = path.as_ref().to_path_buf();
+    let (file, size) = open(&path)?;
+    let mut writer = Writer {
+        path,
+        file: Some(file),
+        size,
+        rotation,
+        buf: Vec::with_capacity(4096),
+    };
+    let periodic = spawn("cantal-recorder", interval, true, move || {
+        writer.write(&*coll);
+    })?;
+    Ok(RecorderGuard {
+        _periodic: periodic,
+    })
+}
+
+/// Periodically append snapshots of the collection to a file
+///
+/// Every `interval` a single line is appended to the file, containing
+/// a JSON object with two keys: `timestamp` (milliseconds since the epoch)
+/// and `metrics` (the collection serialized with `Json`). This is useful
+/// for offline analysis when no cantal agent is running.
+pub fn record<P, C>(path: P, interval: Duration, coll: Arc<C>)
+    -> io::Result<RecorderGuard>
+    where P: AsRef<Path>,
+          C: Collection + Send + Sync + ?Sized + 'static,
+{
+    start(path, interval, None, coll)
+}
+
+/// Same as `record` but rotates file when it grows over `max_size` bytes
+///
+/// Old files are renamed to `path.1`, `path.2`, and so on, up to `keep`
+/// files are retained. If `keep` is zero, the file is just truncated.
+pub fn record_rotating<P, C>(path: P, interval: Duration,
+    max_size: u64, keep: usize, coll: Arc<C>)
+    -> io::Result<RecorderGuard>
+    where P: AsRef<Path>,
+          C: Collection + Send + Sync + ?Sized + 'static,
+{
+    start(path, interval, Some(Rotation { max_size, keep }), coll)
+}
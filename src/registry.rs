@@ -0,0 +1,194 @@
+use std::borrow::Cow;
+use std::sync::{Mutex, RwLock};
+
+use lazy_static::lazy_static;
+
+use collection::{Collection, Visitor, start, retire};
+use error::{Error, ErrorEnum};
+use name::{Name, NameVisitor};
+use value::Value;
+use ActiveCollection;
+
+
+/// Key of the label that contains namespace of a registered collection
+pub const NAMESPACE_KEY: &str = "namespace";
+
+/// Process-global collection of metrics registered by libraries
+///
+/// Use `register` to add collections, and `start_registry` in the
+/// application to export all of them. Every metric gets additional
+/// `namespace` label.
+pub struct Registry {
+    collections: RwLock<Vec<(String, &'static (dyn Collection + Sync))>>,
+    mappings: Mutex<Mappings>,
+}
+
+struct Mappings {
+    started: bool,
+    active: Option<Mapping>,
+    // never unmapped while registry is started, see `relayout`
+    retired: Vec<Mapping>,
+}
+
+struct Mapping(ActiveCollection<'static>);
+
+// Mapping is only touched with the mutex locked, and the pointers it
+// contains refer to the shared memory which is not tied to a thread
+unsafe impl Send for Mapping {}
+
+/// Guard returned by `start_registry`
+///
+/// When dropped, metrics of the registry are not exported any more.
+pub struct RegistryGuard {
+    _private: (),
+}
+
+struct NamespaceVisitor<'a, 'x: 'a> {
+    inner: &'a mut dyn Visitor<'x>,
+    namespace: &'a str,
+}
+
+struct NamespaceName<'a> {
+    inner: &'a dyn Name,
+    namespace: &'a str,
+}
+
+struct NamespaceNameVisitor<'a> {
+    inner: &'a mut dyn NameVisitor,
+}
+
+lazy_static! {
+    static ref REGISTRY: Registry = Registry {
+        collections: RwLock::new(Vec::new()),
+        mappings: Mutex::new(Mappings {
+            started: false,
+            active: None,
+            retired: Vec::new(),
+        }),
+    };
+}
+
+/// Returns process-global registry of metrics
+pub fn registry() -> &'static Registry {
+    &REGISTRY
+}
+
+/// Register a collection in the global registry
+///
+/// This is meant to be used by libraries to export their metrics without
+/// knowing how the application exports metrics. If registry is already
+/// started, metrics are moved to the new file having all the collections.
+/// Errors of doing so are logged.
+///
+/// Every such move keeps the previous file mapped until the registry guard
+/// is dropped (a process registers a handful of collections, so this is
+/// bounded). Updates made concurrently with the move may be lost: a value
+/// is copied to the new file and then its pointer is switched, increments
+/// in between go to the old file.
+///
+/// Note: if you implement `Value` yourself, `copy_assign` must read current
+/// value through the pointer to keep values intact on such move.
+pub fn register<C>(namespace: &str, coll: &'static C)
+    where C: Collection + Sync,
+{
+    REGISTRY.collections.write()
+        .expect("registry is not poisoned")
+        .push((namespace.to_string(), coll));
+    let mut mappings = REGISTRY.mappings.lock()
+        .expect("registry is not poisoned");
+    if mappings.started {
+        if let Err(e) = mappings.relayout() {
+            error!("Can't export metrics of {:?}: {}", namespace, e);
+        }
+    }
+}
+
+/// Start exporting metrics of the global registry
+///
+/// Collections registered later are added to the exported ones
+/// automatically. Only one guard may exist at a time, `RegistryStarted`
+/// error is returned if registry is already started.
+pub fn start_registry() -> Result<RegistryGuard, Error> {
+    let mut mappings = REGISTRY.mappings.lock()
+        .expect("registry is not poisoned");
+    if mappings.started {
+        return Err(ErrorEnum::RegistryStarted.into());
+    }
+    mappings.relayout()?;
+    mappings.started = true;
+    Ok(RegistryGuard { _private: () })
+}
+
+impl Mappings {
+    fn relayout(&mut self) -> Result<(), Error> {
+        let new = start(registry())?;
+        if let Some(mut old) = self.active.take() {
+            retire(&mut old.0);
+            // Some thread might have loaded the pointer to the old memory
+            // and still write to it, so we can't unmap it until the values
+            // are not used any more
+            self.retired.push(old);
+        }
+        self.active = Some(Mapping(new));
+        Ok(())
+    }
+}
+
+impl Drop for RegistryGuard {
+    fn drop(&mut self) {
+        let mut mappings = REGISTRY.mappings.lock()
+            .expect("registry is not poisoned");
+        mappings.started = false;
+        mappings.active.take();
+        mappings.retired.clear();
+    }
+}
+
+impl<'a> NameVisitor for NamespaceNameVisitor<'a> {
+    fn visit_pair(&mut self, key: &str, value: &str) {
+        if key != NAMESPACE_KEY {
+            self.inner.visit_pair(key, value);
+        }
+    }
+    fn visit_invalid(&mut self, key: &str) {
+        if key != NAMESPACE_KEY {
+            self.inner.visit_invalid(key);
+        }
+    }
+}
+
+impl<'a> Name for NamespaceName<'a> {
+    fn get(&self, key: &str) -> Option<Cow<'_, str>> {
+        if key == NAMESPACE_KEY {
+            Some(self.namespace.into())
+        } else {
+            self.inner.get(key)
+        }
+    }
+    fn visit(&self, s: &mut dyn NameVisitor) {
+        self.inner.visit(&mut NamespaceNameVisitor { inner: s });
+        s.visit_pair(NAMESPACE_KEY, self.namespace);
+    }
+}
+
+impl<'a, 'x: 'a> Visitor<'x> for NamespaceVisitor<'a, 'x> {
+    fn metric(&mut self, name: &dyn Name, value: &'x dyn Value) {
+        self.inner.metric(&NamespaceName {
+            inner: name,
+            namespace: self.namespace,
+        }, value);
+    }
+}
+
+impl Collection for Registry {
+    fn visit<'x>(&'x self, visitor: &mut dyn Visitor<'x>) {
+        let collections = self.collections.read()
+            .expect("registry is not poisoned");
+        for &(ref namespace, coll) in collections.iter() {
+            coll.visit(&mut NamespaceVisitor {
+                inner: visitor,
+                namespace,
+            });
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use std::cell::Cell;
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use atomic::Atomic;
+use libc::c_void;
+use serde_json;
+
+use collection::{Collection, Visitor};
+use name::Name;
+use periodic::{Periodic, spawn};
+use value::{Value, Describe, RawType, Assign};
+
+
+// a cache line, so shards updated by different CPUs don't interfere
+#[repr(align(64))]
+struct Shard(Atomic<u64>);
+
+/// A counter that is cheap to increment from many threads at once
+///
+/// Every thread increments its own shard, so threads don't fight for
+/// a single cache line. Reading the value sums up all the shards.
+///
+/// Value in the file read by cantal agent is updated only on `flush`, use
+/// `flush_sharded` to do that periodically. Values exported by `serve_http`,
+/// `prometheus` and others are always up to date.
+pub struct ShardedCounter {
+    shards: Box<[Shard]>,
+    #[allow(dead_code)]
+    value: Box<Atomic<u64>>,
+    pointer: AtomicPtr<Atomic<u64>>,
+}
+
+/// A guard of the thread started by `flush_sharded`
+///
+/// When dropped, counters are flushed one last time and the thread is
+/// stopped.
+pub struct ShardedFlusherGuard {
+    _periodic: Periodic,
+}
+
+static NEXT_THREAD: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static THREAD_INDEX: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+fn thread_index() -> usize {
+    THREAD_INDEX.with(|idx| match idx.get() {
+        Some(idx) => idx,
+        None => {
+            let new = NEXT_THREAD.fetch_add(1, Ordering::Relaxed);
+            idx.set(Some(new));
+            new
+        }
+    })
+}
+
+impl ShardedCounter {
+    /// Create a new counter with a shard per CPU
+    pub fn new() -> ShardedCounter {
+        let cpus = thread::available_parallelism()
+            .map(|x| x.get()).unwrap_or(16);
+        ShardedCounter::with_shards(cpus)
+    }
+    /// Create a new counter with the specified number of shards
+    pub fn with_shards(shards: usize) -> ShardedCounter {
+        let tmp = Box::new(Atomic::new(0));
+        ShardedCounter {
+            shards: (0..shards.max(1)).map(|_| Shard(Atomic::new(0)))
+                .collect::<Vec<_>>().into_boxed_slice(),
+            pointer: AtomicPtr::new(&*tmp as *const _ as *mut _),
+            value: tmp,
+        }
+    }
+    /// Increase a counter for ``val``
+    pub fn incr(&self, val: u64) {
+        let shard = &self.shards[thread_index() % self.shards.len()];
+        shard.0.fetch_add(val, Ordering::Relaxed);
+    }
+    /// Get current value for counter
+    pub fn get(&self) -> u64 {
+        self.shards.iter().fold(0u64, |sum, shard| {
+            sum.wrapping_add(shard.0.load(Ordering::Relaxed))
+        })
+    }
+    /// Write current value to the file read by cantal agent
+    pub fn flush(&self) {
+        unsafe {
+            &*self.pointer.load(Ordering::SeqCst)
+        }.store(self.get(), Ordering::Relaxed);
+    }
+}
+
+impl Default for ShardedCounter {
+    fn default() -> ShardedCounter {
+        ShardedCounter::new()
+    }
+}
+
+impl fmt::Display for ShardedCounter {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}", self.get())
+    }
+}
+
+impl fmt::Debug for ShardedCounter {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "ShardedCounter({})", self.get())
+    }
+}
+
+impl Describe for ShardedCounter {
+    fn raw_type(&self) -> RawType { RawType::Counter }
+    fn raw_size(&self) -> usize { 8 }
+    fn as_json(&self) -> serde_json::Value {
+        serde_json::Value::Number(self.get().into())
+    }
+}
+
+impl Assign for ShardedCounter {
+    fn copy_assign(&self, ptr: *mut c_void) {
+        let ptr = ptr as *mut Atomic<u64>;
+        unsafe { (*ptr).store(self.get(), Ordering::SeqCst) };
+        self.pointer.store(ptr, Ordering::SeqCst);
+    }
+    fn assign(&self, ptr: *mut c_void) {
+        // continue from the value in the file, like `Counter` does
+        let ptr = ptr as *mut Atomic<u64>;
+        let old = unsafe { (*ptr).load(Ordering::SeqCst) };
+        self.shards[0].0.fetch_add(old.wrapping_sub(self.get()),
+                                   Ordering::Relaxed);
+        self.pointer.store(ptr, Ordering::SeqCst);
+    }
+    fn reset(&self) {
+        self.value.store(self.get(), Ordering::SeqCst);
+        self.pointer.store(&*self.value as *const _ as *mut _,
+                           Ordering::SeqCst);
+    }
+    fn flush(&self) {
+        ShardedCounter::flush(self)
+    }
+}
+impl Value for ShardedCounter {}
+
+struct FlushVisitor;
+
+impl<'a> Visitor<'a> for FlushVisitor {
+    fn metric(&mut self, _name: &dyn Name, value: &'a dyn Value) {
+        value.flush();
+    }
+}
+
+/// Flush sharded counters of the collection every `interval`
+///
+/// Other values in the collection are not affected.
+pub fn flush_sharded<C>(interval: Duration, coll: Arc<C>)
+    -> io::Result<ShardedFlusherGuard>
+    where C: Collection + Send + Sync + ?Sized + 'static,
+{
+    let periodic = spawn("cantal-flush", interval, true, move || {
+        coll.visit(&mut FlushVisitor);
+    })?;
+    Ok(ShardedFlusherGuard {
+        _periodic: periodic,
+    })
+}
@@ -0,0 +1,277 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::de::{self, Deserialize, Deserializer, SeqAccess, Visitor};
+use serde::ser::{Serialize, Serializer, SerializeSeq};
+
+use name::{Name, NameVisitor};
+
+
+/// Metrics read back from the output of `Json`
+///
+/// ```rust
+/// # extern crate libcantal;
+/// # extern crate serde_json;
+/// # fn main() {
+/// use libcantal::{Snapshot, MetricValue};
+///
+/// let data = r#"[[{"metric": "requests"}, ["counter", 10]]]"#;
+/// let snapshot: Snapshot = serde_json::from_str(data).unwrap();
+/// assert_eq!(snapshot.metrics[0].value, MetricValue::Counter(10));
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Snapshot {
+    /// All the metrics in the order they were serialized
+    pub metrics: Vec<Metric>,
+}
+
+/// A single metric of a `Snapshot`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Metric {
+    /// Name of the metric as key-value pairs
+    pub name: BTreeMap<String, String>,
+    /// The value of the metric
+    pub value: MetricValue,
+}
+
+/// Value of a metric in a `Snapshot`
+#[derive(Debug, Clone, PartialEq)]
+pub enum MetricValue {
+    /// An ever-increasing counter
+    Counter(u64),
+    /// Level (gauge) with integer value
+    Integer(i64),
+    /// Level (gauge) with floating point value
+    Float(f64),
+    /// A string value
+    State(String),
+}
+
+pub struct PairsVisitor<'a>(pub &'a mut BTreeMap<String, String>);
+
+impl<'a> NameVisitor for PairsVisitor<'a> {
+    fn visit_pair(&mut self, key: &str, value: &str) {
+        self.0.insert(key.to_string(), value.to_string());
+    }
+}
+
+impl Snapshot {
+    /// Find the value of a metric with exactly the same name
+    pub fn get(&self, name: &dyn Name) -> Option<&MetricValue> {
+        let mut pairs = BTreeMap::new();
+        name.visit(&mut PairsVisitor(&mut pairs));
+        self.metrics.iter()
+            .find(|m| m.name == pairs)
+            .map(|m| &m.value)
+    }
+}
+
+impl MetricValue {
+    /// Returns numeric value, or `None` for a state
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            MetricValue::Counter(x) => Some(x as f64),
+            MetricValue::Integer(x) => Some(x as f64),
+            MetricValue::Float(x) => Some(x),
+            MetricValue::State(_) => None,
+        }
+    }
+    fn type_str(&self) -> &'static str {
+        match *self {
+            MetricValue::Counter(_) => "counter",
+            MetricValue::Integer(_) | MetricValue::Float(_) => "level",
+            MetricValue::State(_) => "state",
+        }
+    }
+}
+
+impl Serialize for MetricValue {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        let mut seq = serializer.serialize_seq(Some(2))?;
+        seq.serialize_element(self.type_str())?;
+        match *self {
+            MetricValue::Counter(x) => seq.serialize_element(&x)?,
+            MetricValue::Integer(x) => seq.serialize_element(&x)?,
+            MetricValue::Float(x) => seq.serialize_element(&x)?,
+            MetricValue::State(ref x) => seq.serialize_element(x)?,
+        }
+        seq.end()
+    }
+}
+
+impl Serialize for Metric {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        (&self.name, &self.value).serialize(serializer)
+    }
+}
+
+impl Serialize for Snapshot {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        self.metrics.serialize(serializer)
+    }
+}
+
+/// Level is serialized as plain number, so we pick type by its contents
+struct Level(MetricValue);
+
+impl<'de> Deserialize<'de> for Level {
+    fn deserialize<D>(deserializer: D) -> Result<Level, D::Error>
+        where D: Deserializer<'de>
+    {
+        struct LevelVisitor;
+        impl<'de> Visitor<'de> for LevelVisitor {
+            type Value = Level;
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a number")
+            }
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<Level, E> {
+                Ok(Level(MetricValue::Integer(v)))
+            }
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<Level, E> {
+                if v > i64::MAX as u64 {
+                    Ok(Level(MetricValue::Float(v as f64)))
+                } else {
+                    Ok(Level(MetricValue::Integer(v as i64)))
+                }
+            }
+            fn visit_f64<E: de::Error>(self, v: f64) -> Result<Level, E> {
+                Ok(Level(MetricValue::Float(v)))
+            }
+        }
+        deserializer.deserialize_any(LevelVisitor)
+    }
+}
+
+impl<'de> Deserialize<'de> for MetricValue {
+    fn deserialize<D>(deserializer: D) -> Result<MetricValue, D::Error>
+        where D: Deserializer<'de>
+    {
+        struct ValueVisitor;
+        impl<'de> Visitor<'de> for ValueVisitor {
+            type Value = MetricValue;
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a pair of value type and value")
+            }
+            fn visit_seq<A>(self, mut seq: A) -> Result<MetricValue, A::Error>
+                where A: SeqAccess<'de>
+            {
+                let kind: String = seq.next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+                let value = match &kind[..] {
+                    "counter" => seq.next_element()?
+                        .map(MetricValue::Counter),
+                    "level" => seq.next_element()?
+                        .map(|Level(x)| x),
+                    "state" => seq.next_element()?
+                        .map(MetricValue::State),
+                    _ => return Err(de::Error::unknown_variant(&kind,
+                        &["counter", "level", "state"])),
+                };
+                value.ok_or_else(|| de::Error::invalid_length(1, &self))
+            }
+        }
+        deserializer.deserialize_seq(ValueVisitor)
+    }
+}
+
+impl<'de> Deserialize<'de> for Metric {
+    fn deserialize<D>(deserializer: D) -> Result<Metric, D::Error>
+        where D: Deserializer<'de>
+    {
+        let (name, value) = Deserialize::deserialize(deserializer)?;
+        Ok(Metric { name, value })
+    }
+}
+
+impl<'de> Deserialize<'de> for Snapshot {
+    fn deserialize<D>(deserializer: D) -> Result<Snapshot, D::Error>
+        where D: Deserializer<'de>
+    {
+        Ok(Snapshot {
+            metrics: Deserialize::deserialize(deserializer)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::{from_str, to_string};
+
+    use {Counter, Integer, State, Value, Json};
+    use super::{Snapshot, MetricValue};
+
+    fn read(data: &str) -> Snapshot {
+        from_str(data).expect("valid snapshot")
+    }
+
+    fn error(data: &str) -> String {
+        from_str::<Snapshot>(data).expect_err("invalid snapshot")
+            .to_string()
+    }
+
+    #[test]
+    fn collection() {
+        let counter = Counter::new();
+        let integer = Integer::new();
+        let state = State::new();
+        counter.incr(10);
+        integer.set(-5);
+        state.set("running");
+        let metrics = vec![
+            (json!({"metric": "counter"}), &counter as &dyn Value),
+            (json!({"metric": "integer", "group": "x"}), &integer),
+            (json!({"metric": "state"}), &state),
+        ];
+        let snapshot = read(&to_string(&Json(&metrics)).unwrap());
+        assert_eq!(snapshot.metrics.len(), 3);
+        assert_eq!(snapshot.get(&json!({"metric": "counter"})),
+                   Some(&MetricValue::Counter(10)));
+        assert_eq!(snapshot.get(&json!({"group": "x", "metric": "integer"})),
+                   Some(&MetricValue::Integer(-5)));
+        assert_eq!(snapshot.get(&json!({"metric": "state"})),
+                   Some(&MetricValue::State("running".into())));
+        assert_eq!(read(&to_string(&snapshot).unwrap()), snapshot);
+    }
+
+    #[test]
+    fn levels() {
+        let snapshot = read(r#"[
+            [{"metric": "int"}, ["level", 7]],
+            [{"metric": "float"}, ["level", 0.5]],
+            [{"metric": "whole"}, ["level", 2.0]],
+            [{"metric": "large"}, ["level", 18446744073709551615]]
+        ]"#);
+        let values = snapshot.metrics.iter()
+            .map(|m| m.value.clone()).collect::<Vec<_>>();
+        assert_eq!(values, vec![
+            MetricValue::Integer(7),
+            MetricValue::Float(0.5),
+            MetricValue::Float(2.0),
+            // doesn't fit i64
+            MetricValue::Float(18446744073709551615u64 as f64),
+        ]);
+        assert_eq!(read(&to_string(&snapshot).unwrap()), snapshot);
+    }
+
+    #[test]
+    fn errors() {
+        assert!(error(r#"[[{"metric": "x"}, ["gauge", 1]]]"#)
+            .starts_with("unknown variant `gauge`, \
+                          expected one of `counter`, `level`, `state`"));
+        assert!(error(r#"[[{"metric": "x"}, ["counter"]]]"#)
+            .starts_with("invalid length 1, \
+                          expected a pair of value type and value"));
+        assert!(error(r#"[[{"metric": "x"}, []]]"#)
+            .starts_with("invalid length 0, \
+                          expected a pair of value type and value"));
+        assert!(error(r#"[[{"metric": "x"}, ["level", "1"]]]"#)
+            .starts_with("invalid type: string \"1\", expected a number"));
+    }
+}
@@ -0,0 +1,152 @@
+use std::fmt;
+use std::ptr;
+use std::sync::{Mutex, MutexGuard};
+use std::sync::atomic::{AtomicPtr, Ordering};
+
+use libc::c_void;
+use serde_json;
+
+use time::now_ms;
+use value::{Value, Describe, RawType, Assign};
+
+
+const DEFAULT_CAPACITY: usize = 248;
+
+/// A kind of metric (`Value`) that exports a string
+///
+/// Stored as a timestamp of the last change (in milliseconds) followed by
+/// a string of a fixed capacity. Longer strings are truncated.
+pub struct State {
+    heap: *mut u8,
+    size: usize,
+    pointer: AtomicPtr<u8>,
+    // serializes writers, as string can't be written atomically
+    lock: Mutex<()>,
+}
+
+// all the accesses to the memory are under the lock
+unsafe impl Send for State {}
+unsafe impl Sync for State {}
+
+impl State {
+    /// Create a new state value with default capacity (248 bytes)
+    ///
+    /// Note you need to export it in a collection to make it visible for
+    /// cantal agent
+    pub fn new() -> State {
+        State::with_capacity(DEFAULT_CAPACITY)
+    }
+    /// Create a new state value which can store `capacity` bytes of string
+    ///
+    /// Capacity is rounded up to a multiple of 8.
+    pub fn with_capacity(capacity: usize) -> State {
+        let size = 8 + ((capacity + 7) & !7);
+        let heap = Box::into_raw(vec![0u8; size].into_boxed_slice())
+            as *mut u8;
+        State {
+            heap,
+            size,
+            pointer: AtomicPtr::new(heap),
+            lock: Mutex::new(()),
+        }
+    }
+    fn lock(&self) -> MutexGuard<'_, ()> {
+        // nothing can be broken by a panic, so ignore poisoning
+        self.lock.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    /// Set the value, updating timestamp
+    pub fn set(&self, value: &str) {
+        let mut len = value.len().min(self.size - 8);
+        while !value.is_char_boundary(len) {
+            len -= 1;
+        }
+        let timestamp = now_ms();
+        let _lock = self.lock();
+        let ptr = self.pointer.load(Ordering::SeqCst);
+        unsafe {
+            ptr::write_unaligned(ptr as *mut u64, timestamp);
+            ptr::copy_nonoverlapping(value.as_ptr(), ptr.add(8), len);
+            ptr::write_bytes(ptr.add(8 + len), 0, self.size - 8 - len);
+        }
+    }
+    /// Get current value
+    pub fn get(&self) -> String {
+        let _lock = self.lock();
+        let ptr = self.pointer.load(Ordering::SeqCst);
+        let data = unsafe {
+            ::std::slice::from_raw_parts(ptr.add(8), self.size - 8)
+        };
+        let len = data.iter().position(|&x| x == 0).unwrap_or(data.len());
+        String::from_utf8_lossy(&data[..len]).into_owned()
+    }
+    /// Get timestamp of the last change in milliseconds since unix epoch
+    ///
+    /// Returns zero if value has never been set.
+    pub fn timestamp(&self) -> u64 {
+        let _lock = self.lock();
+        unsafe {
+            ptr::read_unaligned(self.pointer.load(Ordering::SeqCst)
+                                as *const u64)
+        }
+    }
+}
+
+impl Default for State {
+    fn default() -> State {
+        State::new()
+    }
+}
+
+impl Drop for State {
+    fn drop(&mut self) {
+        unsafe {
+            drop(Box::from_raw(
+                ptr::slice_from_raw_parts_mut(self.heap, self.size)));
+        }
+    }
+}
+
+impl fmt::Display for State {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str(&self.get())
+    }
+}
+
+impl fmt::Debug for State {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "State({:?})", self.get())
+    }
+}
+
+impl Describe for State {
+    fn raw_type(&self) -> RawType { RawType::State }
+    fn raw_size(&self) -> usize { self.size }
+    fn as_json(&self) -> serde_json::Value {
+        serde_json::Value::String(self.get())
+    }
+}
+
+impl Assign for State {
+    fn copy_assign(&self, ptr: *mut c_void) {
+        let _lock = self.lock();
+        let ptr = ptr as *mut u8;
+        unsafe {
+            ptr::copy_nonoverlapping(self.pointer.load(Ordering::SeqCst),
+                                     ptr, self.size);
+        }
+        self.pointer.store(ptr, Ordering::SeqCst);
+    }
+    fn assign(&self, ptr: *mut c_void) {
+        let _lock = self.lock();
+        self.pointer.store(ptr as *mut u8, Ordering::SeqCst);
+    }
+    fn reset(&self) {
+        let _lock = self.lock();
+        let ptr = self.pointer.load(Ordering::SeqCst);
+        if ptr != self.heap {
+            unsafe { ptr::copy_nonoverlapping(ptr, self.heap, self.size) };
+            self.pointer.store(self.heap, Ordering::SeqCst);
+        }
+    }
+}
+impl Value for State {}
@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+use std::io;
+use std::net::{UdpSocket, ToSocketAddrs, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use collection::{Collection, Visitor};
+use name::Name;
+use path::dotted_path;
+use periodic::{spawn, Periodic};
+use value::{Value, RawType};
+
+
+/// Keep packets below typical MTU to avoid fragmentation
+const MAX_PACKET: usize = 1432;
+const PATH_ORDER: &[&str] = &["group", "metric"];
+
+/// A guard of the thread started by `push_statsd`
+///
+/// When dropped, metrics are pushed one last time and the thread is stopped.
+pub struct StatsdPusherGuard {
+    _periodic: Periodic,
+}
+
+struct Pusher {
+    socket: UdpSocket,
+    counters: HashMap<String, u64>,
+}
+
+struct LinesVisitor<'a> {
+    counters: &'a mut HashMap<String, u64>,
+    lines: &'a mut Vec<String>,
+}
+
+impl<'a, 'b> Visitor<'b> for LinesVisitor<'a> {
+    fn metric(&mut self, name: &dyn Name, value: &dyn Value) {
+        let mut path = String::with_capacity(64);
+        dotted_path(name, PATH_ORDER, &mut path);
+        let json = value.as_json();
+        match value.raw_type() {
+            RawType::Counter => {
+                let cur = json.as_u64().unwrap_or(0);
+                let old = self.counters.insert(path.clone(), cur).unwrap_or(0);
+                // counter may be reset by a `start_with_reading`
+                let delta = if cur >= old { cur - old } else { cur };
+                if delta > 0 {
+                    self.lines.push(format!("{}:{}|c", path, delta));
+                }
+            }
+            RawType::Level(_) => {
+                if json.as_f64().map(|x| x < 0.).unwrap_or(false) {
+                    // negative value is a decrement for statsd gauges
+                    self.lines.push(format!("{}:0|g", path));
+                }
+                self.lines.push(format!("{}:{}|g", path, json));
+            }
+            RawType::State => {}
+        }
+    }
+}
+
+impl Pusher {
+    fn push<C: Collection + ?Sized>(&mut self, coll: &C) {
+        let mut lines = Vec::new();
+        coll.visit(&mut LinesVisitor {
+            counters: &mut self.counters,
+            lines: &mut lines,
+        });
+        let mut packet = String::with_capacity(MAX_PACKET);
+        for line in lines {
+            if !packet.is_empty() &&
+                packet.len() + line.len() + 1 > MAX_PACKET
+            {
+                self.send(&packet);
+                packet.clear();
+            }
+            if !packet.is_empty() {
+                packet.push('\n');
+            }
+            packet.push_str(&line);
+        }
+        if !packet.is_empty() {
+            self.send(&packet);
+        }
+    }
+    fn send(&self, packet: &str) {
+        match self.socket.send(packet.as_bytes()) {
+            Ok(_) => {}
+            Err(e) => debug!("Error sending statsd packet: {}", e),
+        }
+    }
+}
+
+/// Periodically push metrics of a collection to a statsd server over UDP
+///
+/// Metric names are dotted paths built from the values of the name:
+/// `group` and `metric` go first, then other keys in sorted order. Counters
+/// are sent as deltas since the previous push and levels as gauges. State
+/// values are not sent.
+pub fn push_statsd<A, C>(addr: A, interval: Duration, coll: Arc<C>)
+    -> io::Result<StatsdPusherGuard>
+    where A: ToSocketAddrs,
+          C: Collection + Send + Sync + ?Sized + 'static,
+{
+    let addr = addr.to_socket_addrs()?.next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput,
+                                      "no address to send metrics to"))?;
+    let socket = match addr {
+        SocketAddr::V4(_) => UdpSocket::bind("0.0.0.0:0")?,
+        SocketAddr::V6(_) => UdpSocket::bind("[::]:0")?,
+    };
+    socket.connect(addr)?;
+    let mut pusher = Pusher {
+        socket,
+        counters: HashMap::new(),
+    };
+    let periodic = spawn("cantal-statsd", interval, true, move || {
+        pusher.push(&*coll);
+    })?;
+    Ok(StatsdPusherGuard {
+        _periodic: periodic,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::net::UdpSocket;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use counter::Counter;
+    use integer::Integer;
+    use value::Value;
+    use super::{Pusher, MAX_PACKET, push_statsd};
+
+    fn receiver() -> UdpSocket {
+        let sock = UdpSocket::bind("127.0.0.1:0").expect("can bind");
+        sock.set_read_timeout(Some(Duration::from_secs(5)))
+            .expect("can set timeout");
+        sock
+    }
+
+    fn pusher(receiver: &UdpSocket) -> Pusher {
+        let socket = UdpSocket::bind("127.0.0.1:0").expect("can bind");
+        socket.connect(receiver.local_addr().expect("bound"))
+            .expect("can connect");
+        Pusher {
+            socket,
+            counters: HashMap::new(),
+        }
+    }
+
+    fn recv(sock: &UdpSocket) -> String {
+        let mut buf = [0u8; 65536];
+        let bytes = sock.recv(&mut buf).expect("packet received");
+        String::from_utf8(buf[..bytes].to_vec()).expect("valid utf-8")
+    }
+
+    #[test]
+    fn counter_deltas() {
+        let sock = receiver();
+        let mut pusher = pusher(&sock);
+        let counter = Counter::new();
+        let metrics = vec![
+            (json!({"group": "http", "metric": "requests"}),
+             &counter as &dyn Value),
+        ];
+        counter.incr(5);
+        pusher.push(&metrics);
+        assert_eq!(recv(&sock), "http.requests:5|c");
+        counter.incr(3);
+        pusher.push(&metrics);
+        assert_eq!(recv(&sock), "http.requests:3|c");
+    }
+
+    #[test]
+    fn counter_reset() {
+        let sock = receiver();
+        let mut pusher = pusher(&sock);
+        let old = Counter::new();
+        old.incr(10);
+        pusher.push(&vec![
+            (json!({"metric": "requests"}), &old as &dyn Value),
+        ]);
+        assert_eq!(recv(&sock), "requests:10|c");
+        // i.e. process restarted without `start_with_reading`
+        let new = Counter::new();
+        new.incr(3);
+        pusher.push(&vec![
+            (json!({"metric": "requests"}), &new as &dyn Value),
+        ]);
+        assert_eq!(recv(&sock), "requests:3|c");
+    }
+
+    #[test]
+    fn negative_gauge() {
+        let sock = receiver();
+        let mut pusher = pusher(&sock);
+        let level = Integer::new();
+        let metrics = vec![(json!({"metric": "level"}), &level as &dyn Value)];
+        level.set(7);
+        pusher.push(&metrics);
+        assert_eq!(recv(&sock), "level:7|g");
+        level.set(-5);
+        pusher.push(&metrics);
+        assert_eq!(recv(&sock), "level:0|g\nlevel:-5|g");
+    }
+
+    #[test]
+    fn packet_splitting() {
+        let sock = receiver();
+        let mut pusher = pusher(&sock);
+        let counters = (0..100).map(|_| Counter::new()).collect::<Vec<_>>();
+        let metrics = counters.iter().enumerate().map(|(i, c)| {
+            c.incr(1);
+            (json!({"metric": format!("{:040}", i)}), c as &dyn Value)
+        }).collect::<Vec<_>>();
+        pusher.push(&metrics);
+        let mut lines = Vec::new();
+        let mut packets = 0;
+        while lines.len() < counters.len() {
+            let packet = recv(&sock);
+            assert!(packet.len() <= MAX_PACKET, "{}", packet.len());
+            lines.extend(packet.lines().map(|x| x.to_string()));
+            packets += 1;
+        }
+        assert!(packets > 1);
+        assert_eq!(lines.len(), counters.len());
+        assert_eq!(lines[99], format!("{:040}:1|c", 99));
+    }
+
+    #[test]
+    fn flush_on_drop() {
+        let sock = receiver();
+        let counter: &'static Counter = Box::leak(Box::new(Counter::new()));
+        let metrics = Arc::new(vec![
+            (json!({"metric": "requests"}), counter as &dyn Value),
+        ]);
+        let guard = push_statsd(sock.local_addr().expect("bound"),
+            Duration::from_secs(3600), metrics).expect("can push");
+        counter.incr(2);
+        drop(guard);
+        assert_eq!(recv(&sock), "requests:2|c");
+    }
+}
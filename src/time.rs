@@ -0,0 +1,14 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+
+/// Duration in whole milliseconds
+pub fn millis(dur: Duration) -> u64 {
+    dur.as_secs() * 1000 + u64::from(dur.subsec_millis())
+}
+
+/// Current time in milliseconds since the epoch, zero if clock is broken
+pub fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH)
+        .map(millis)
+        .unwrap_or(0)
+}
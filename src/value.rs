@@ -5,6 +5,7 @@ use serde_json;
 
 
 /// A kind of level (gauge) metric, only used for `Value` trait
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum LevelKind {
     /// Signed integer gauge type
     Signed,
@@ -13,6 +14,7 @@ pub enum LevelKind {
 }
 
 /// A raw type of metric, only used for `Value` trait
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RawType {
     /// Level or gauge type of metric
     Level(LevelKind),
@@ -25,13 +27,18 @@ pub enum RawType {
 /// A value stored in a collection
 ///
 /// This is an umbrella trait that you can't implement outside of this crate.
-pub trait Value: Display + Debug + Describe + Assign {
+///
+/// All values are thread-safe, so collections of them may be exported from
+/// a background thread (see `serve_http`).
+pub trait Value: Display + Debug + Describe + Assign + Send + Sync {
 }
 
 pub trait Assign {
     fn assign(&self, ptr: *mut c_void);
     fn copy_assign(&self, ptr: *mut c_void);
     fn reset(&self);
+    /// Write buffered value to the memory read by cantal agent
+    fn flush(&self) {}
 }
 
 pub trait Describe {
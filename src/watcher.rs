@@ -0,0 +1,298 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde_json::Value as Json;
+
+use collection::{Collection, Visitor};
+use encode::NameEncoder;
+use name::Name;
+use periodic::{Periodic, spawn};
+use value::{Value, RawType};
+
+
+#[derive(Debug, Clone, Copy)]
+enum Condition {
+    Above { enter: f64, exit: f64 },
+    Below { enter: f64, exit: f64 },
+    RateAbove { enter: f64, exit: f64 },
+    Stale(Duration),
+}
+
+struct Previous {
+    value: Json,
+    time: Instant,
+    changed: Instant,
+}
+
+/// A condition on a single metric, see `Watcher`
+///
+/// To avoid flapping, every rule except `stale` has two thresholds: the
+/// rule becomes active when the value crosses `enter` threshold and
+/// becomes inactive only when it crosses `exit` threshold back.
+///
+/// Callbacks receive the value that triggered them: the value of the metric
+/// for `above` and `below`, the rate per second for `rate_above` and
+/// seconds since last change for `stale`.
+pub struct Rule {
+    name: String,
+    condition: Condition,
+    on_enter: Option<Box<dyn FnMut(f64) + Send>>,
+    on_exit: Option<Box<dyn FnMut(f64) + Send>>,
+    active: bool,
+    previous: Option<Previous>,
+}
+
+/// A set of rules evaluated over metrics of a collection
+///
+/// ```rust
+/// # extern crate libcantal;
+/// # use std::time::Duration;
+/// # use libcantal::{Integer, Value, Watcher, Rule};
+/// # fn main() {
+/// let queue = Integer::new();
+/// let metrics: &[(&[(&str, &str)], &dyn Value)] = &[
+///     (&[("metric", "queue_size")], &queue),
+/// ];
+/// let name: &[(&str, &str)] = &[("metric", "queue_size")];
+/// let mut watcher = Watcher::new()
+///     .rule(Rule::above(&name, 1000., 800.)
+///         .on_enter(|size| println!("shedding load, queue is {}", size))
+///         .on_exit(|size| println!("accepting again, queue is {}", size)));
+/// queue.set(1500);
+/// watcher.check(metrics);
+/// assert!(watcher.rules()[0].is_active());
+/// # }
+/// ```
+///
+/// Use `watch` to check rules periodically in a background thread.
+pub struct Watcher {
+    rules: Vec<Rule>,
+    // canonical name -> indexes of rules
+    index: HashMap<String, Vec<usize>>,
+}
+
+/// A guard of the thread started by `watch`
+///
+/// When dropped, the thread is stopped.
+pub struct WatcherGuard {
+    _periodic: Periodic,
+}
+
+struct CheckVisitor<'a> {
+    now: Instant,
+    encoder: NameEncoder,
+    buf: String,
+    watcher: &'a mut Watcher,
+}
+
+fn seconds(dur: Duration) -> f64 {
+    dur.as_secs() as f64 + dur.subsec_nanos() as f64 / 1e9
+}
+
+fn canonical(name: &dyn Name) -> String {
+    let mut buf = String::with_capacity(64);
+    NameEncoder::new().encode(name, &mut buf);
+    buf
+}
+
+impl Rule {
+    fn new(name: &dyn Name, condition: Condition) -> Rule {
+        Rule {
+            name: canonical(name),
+            condition,
+            on_enter: None,
+            on_exit: None,
+            active: false,
+            previous: None,
+        }
+    }
+    /// Active when value is above `enter` until it drops below `exit`
+    ///
+    /// `exit` should not be larger than `enter`.
+    pub fn above(name: &dyn Name, enter: f64, exit: f64) -> Rule {
+        Rule::new(name, Condition::Above { enter, exit })
+    }
+    /// Active when value is below `enter` until it grows above `exit`
+    ///
+    /// `exit` should not be smaller than `enter`.
+    pub fn below(name: &dyn Name, enter: f64, exit: f64) -> Rule {
+        Rule::new(name, Condition::Below { enter, exit })
+    }
+    /// Active when value grows faster than `enter` per second until the
+    /// rate drops below `exit`
+    ///
+    /// Rate is calculated between two consecutive checks. Counter resets
+    /// (i.e. by `start_with_reading`) are taken into account.
+    pub fn rate_above(name: &dyn Name, enter: f64, exit: f64) -> Rule {
+        Rule::new(name, Condition::RateAbove { enter, exit })
+    }
+    /// Active when value hasn't changed for `timeout` until it changes
+    ///
+    /// Works for states too.
+    pub fn stale(name: &dyn Name, timeout: Duration) -> Rule {
+        Rule::new(name, Condition::Stale(timeout))
+    }
+    /// Call `f` when rule becomes active
+    pub fn on_enter<F: FnMut(f64) + Send + 'static>(mut self, f: F) -> Rule {
+        self.on_enter = Some(Box::new(f));
+        self
+    }
+    /// Call `f` when rule becomes inactive
+    pub fn on_exit<F: FnMut(f64) + Send + 'static>(mut self, f: F) -> Rule {
+        self.on_exit = Some(Box::new(f));
+        self
+    }
+    /// Returns true if the condition currently holds
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+    // returns whether the rule should be active and the value for callbacks
+    fn evaluate(&self, now: Instant, raw_type: RawType, value: &Json)
+        -> Option<(bool, f64)>
+    {
+        use self::Condition::*;
+        let active = self.active;
+        match self.condition {
+            Above { enter, exit } => {
+                let x = value.as_f64()?;
+                Some((if active { x >= exit } else { x > enter }, x))
+            }
+            Below { enter, exit } => {
+                let x = value.as_f64()?;
+                Some((if active { x <= exit } else { x < enter }, x))
+            }
+            RateAbove { enter, exit } => {
+                let x = value.as_f64()?;
+                let prev = self.previous.as_ref()?;
+                let old = prev.value.as_f64()?;
+                let elapsed = seconds(now.duration_since(prev.time));
+                if elapsed <= 0. {
+                    return None;
+                }
+                let delta = match raw_type {
+                    RawType::Counter if x < old => x,
+                    _ => x - old,
+                };
+                let rate = delta / elapsed;
+                Some((if active { rate >= exit } else { rate > enter }, rate))
+            }
+            Stale(timeout) => {
+                let prev = self.previous.as_ref()?;
+                if prev.value != *value {
+                    return Some((false, 0.));
+                }
+                let unchanged = now.duration_since(prev.changed);
+                Some((unchanged >= timeout, seconds(unchanged)))
+            }
+        }
+    }
+    fn update(&mut self, now: Instant, value: &dyn Value) {
+        let json = value.as_json();
+        let result = self.evaluate(now, value.raw_type(), &json);
+        if let Some((active, x)) = result {
+            if active != self.active {
+                self.active = active;
+                let callback = if active {
+                    &mut self.on_enter
+                } else {
+                    &mut self.on_exit
+                };
+                if let Some(ref mut callback) = *callback {
+                    callback(x);
+                }
+            }
+        }
+        let changed = match self.previous {
+            Some(ref prev) if prev.value == json => prev.changed,
+            _ => now,
+        };
+        self.previous = Some(Previous { value: json, time: now, changed });
+    }
+}
+
+impl fmt::Debug for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Rule")
+            .field("name", &self.name)
+            .field("condition", &self.condition)
+            .field("active", &self.active)
+            .finish()
+    }
+}
+
+impl<'a, 'b> Visitor<'b> for CheckVisitor<'a> {
+    fn metric(&mut self, name: &dyn Name, value: &dyn Value) {
+        self.buf.clear();
+        self.encoder.encode(name, &mut self.buf);
+        if let Some(indexes) = self.watcher.index.get(&self.buf) {
+            for &idx in indexes {
+                self.watcher.rules[idx].update(self.now, value);
+            }
+        }
+    }
+}
+
+impl Watcher {
+    /// Create a watcher without rules
+    pub fn new() -> Watcher {
+        Watcher {
+            rules: Vec::new(),
+            index: HashMap::new(),
+        }
+    }
+    /// Add a rule
+    pub fn rule(mut self, rule: Rule) -> Watcher {
+        self.index.entry(rule.name.clone()).or_default()
+            .push(self.rules.len());
+        self.rules.push(rule);
+        self
+    }
+    /// Returns all the rules in the order they were added
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+    /// Evaluate rules over current values of the collection
+    ///
+    /// Callbacks are run in the current thread. Rules whose metric is not
+    /// in the collection are left as is.
+    pub fn check<C: Collection + ?Sized>(&mut self, coll: &C) {
+        coll.visit(&mut CheckVisitor {
+            now: Instant::now(),
+            encoder: NameEncoder::new(),
+            buf: String::with_capacity(64),
+            watcher: self,
+        });
+    }
+}
+
+impl Default for Watcher {
+    fn default() -> Watcher {
+        Watcher::new()
+    }
+}
+
+impl fmt::Debug for Watcher {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Watcher")
+            .field("rules", &self.rules)
+            .finish()
+    }
+}
+
+/// Check rules of the watcher over the collection every `interval`
+///
+/// Callbacks are run in the background thread, so they should be quick.
+pub fn watch<C>(mut watcher: Watcher, interval: Duration, coll: Arc<C>)
+    -> io::Result<WatcherGuard>
+    where C: Collection + Send + Sync + ?Sized + 'static,
+{
+    let periodic = spawn("cantal-watcher", interval, false, move || {
+        watcher.check(&*coll);
+    })?;
+    Ok(WatcherGuard {
+        _periodic: periodic,
+    })
+}